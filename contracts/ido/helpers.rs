@@ -1,8 +1,10 @@
+use ink::env::hash;
 use ink::prelude::vec::Vec;
 use openbrush::{
     contracts::psp22::{PSP22Error, PSP22Ref},
     traits::{AccountId, Balance},
 };
+use crate::types::Domain;
 
 #[inline]
 pub fn safe_transfer(mut token: AccountId, to: AccountId, value: Balance) -> Result<(), PSP22Error> {
@@ -19,6 +21,111 @@ pub fn safe_transfer_from(
     PSP22Ref::transfer_from(&mut token, from, to, value, Vec::new())
 }
 
+/// Blake2x256 of the SCALE encoded `(domain, payload)`
+pub fn hash_payload<P: scale::Encode>(domain: &Domain, payload: &P) -> [u8; 32] {
+    let mut message_hash = <hash::Blake2x256 as hash::HashOutput>::Type::default();
+    ink::env::hash_encoded::<hash::Blake2x256, _>(&(domain, payload), &mut message_hash);
+    message_hash
+}
+
+/// Blake2x256 of a legacy underscore-joined string message
+pub fn hash_message(message: &str) -> [u8; 32] {
+    let mut message_hash = <hash::Blake2x256 as hash::HashOutput>::Type::default();
+    ink::env::hash_bytes::<hash::Blake2x256>(message.as_bytes(), &mut message_hash);
+    message_hash
+}
+
+/// Keccak256 of the hash with the Ethereum personal-sign prefix, as produced by `eth_sign`
+pub fn eth_message_hash(message_hash: &[u8; 32]) -> [u8; 32] {
+    let mut prefixed: Vec<u8> = Vec::from(&b"\x19Ethereum Signed Message:\n32"[..]);
+    prefixed.extend_from_slice(message_hash);
+    let mut eth_hash = <hash::Keccak256 as hash::HashOutput>::Type::default();
+    ink::env::hash_bytes::<hash::Keccak256>(&prefixed, &mut eth_hash);
+    eth_hash
+}
+
+/// allowlist leaf committing `account` to `max_allocation`
+pub fn merkle_leaf(account: AccountId, max_allocation: Balance) -> [u8; 32] {
+    let mut leaf = <hash::Blake2x256 as hash::HashOutput>::Type::default();
+    ink::env::hash_encoded::<hash::Blake2x256, _>(&(account, max_allocation), &mut leaf);
+    leaf
+}
+
+/// hashes two nodes in sorted order so proofs don't need to carry the side of each sibling
+pub fn hash_pair(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let pair = if a <= b { (a, b) } else { (b, a) };
+    let mut node = <hash::Blake2x256 as hash::HashOutput>::Type::default();
+    ink::env::hash_encoded::<hash::Blake2x256, _>(&pair, &mut node);
+    node
+}
+
+pub fn verify_merkle_proof(root: [u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let mut node = leaf;
+    for sibling in proof {
+        node = hash_pair(node, *sibling);
+    }
+    node == root
+}
+
+/// returns `amount * numerator / denominator` rounded down, `numerator` must not exceed `denominator`
+/// so the result always fits, 0 when `denominator` is 0
+#[inline]
+pub fn proportion(amount: Balance, numerator: u128, denominator: u128) -> Balance {
+    mul_div(amount, numerator, denominator, false).unwrap_or(0)
+}
+
+/// returns `a * b / c` with a 256-bit intermediate product, `None` when `c` is 0 or the result does not fit in u128
+pub fn mul_div(a: u128, b: u128, c: u128, round_up: bool) -> Option<u128> {
+    if c == 0 {
+        return None;
+    }
+    let (hi, lo) = mul_wide(a, b);
+    if hi == 0 {
+        let quotient = lo / c;
+        return if round_up && lo % c > 0 { quotient.checked_add(1) } else { Some(quotient) };
+    }
+    if hi >= c {
+        return None;
+    }
+
+    // long division of the 256-bit product, the remainder always stays below `c`
+    let mut remainder = hi;
+    let mut quotient: u128 = 0;
+    for i in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= c {
+            remainder = remainder.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+    if round_up && remainder > 0 { quotient.checked_add(1) } else { Some(quotient) }
+}
+
+/// full product of `a * b` as `(high, low)` 128-bit halves
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    let mask = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & mask);
+    let (b_hi, b_lo) = (b >> 64, b & mask);
+    let low = a_lo * b_lo;
+    let mid_left = a_lo * b_hi;
+    let mid_right = a_hi * b_lo;
+    let mid = (low >> 64) + (mid_left & mask) + (mid_right & mask);
+    let lo = (low & mask) | (mid << 64);
+    let hi = a_hi * b_hi + (mid_left >> 64) + (mid_right >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+/// factors `(up, down)` converting an amount with `from` decimals to `to` decimals as `amount * up / down`
+pub fn decimals_scale(to: u8, from: u8) -> Option<(u128, u128)> {
+    if to >= from {
+        Some(((10 as u128).checked_pow((to - from) as u32)?, 1))
+    } else {
+        Some((1, (10 as u128).checked_pow((from - to) as u32)?))
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 pub enum TransferHelperError {
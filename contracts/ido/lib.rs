@@ -3,7 +3,7 @@
 
 mod helpers;
 pub mod traits;
-mod types;
+pub mod types;
 pub use traits::{*};
 
 #[openbrush::contract]
@@ -12,12 +12,14 @@ pub mod ido {
     use ink::{
         env::{
             hash,
+            call::{build_call, ExecutionInput, Selector},
         },
         codegen::{
             EmitEvent,
             Env,
         },
         prelude::string::{String, ToString},
+        prelude::vec::Vec,
     };
     use openbrush::{
         modifiers,
@@ -25,24 +27,30 @@ pub mod ido {
         contracts::traits::psp22::*,
     };
     use openbrush::contracts::access_control::*;
-    use openbrush::traits::{DefaultEnv};
+    use openbrush::contracts::pausable::*;
+    use openbrush::traits::{DefaultEnv, ZERO_ADDRESS};
     use crate::{ensure, traits, helpers, types};
-    use crate::traits::{IDOError, Internal};
+    use crate::traits::{IDOError, Ido, Internal};
 
     pub const SUB_ADMIN: RoleType = ink::selector_id!("SUB_ADMIN");
+    pub const SIGNER_ADMIN: RoleType = ink::selector_id!("SIGNER_ADMIN");
+    pub const PAUSER: RoleType = ink::selector_id!("PAUSER");
+    /// granted to the deployer of a pool created without its sale configuration, allows the single `init_ido` call
+    pub const INITIALIZER: RoleType = ink::selector_id!("INITIALIZER");
 
     #[ink(event)]
     pub struct InitIdoContract {
         #[ink(topic)]
         pub ido_token: AccountId,
-        pub price: Balance,
-        pub price_decimals: u32,
+        pub price: types::Price,
+        pub token_decimals: u8,
         pub signer: AccountId,
         pub max_issue_ido_amount: u128,
     }
 
     #[ink(event)]
     pub struct BuyTokenWithNative {
+        pub payer: AccountId,
         #[ink(topic)]
         pub buyer: AccountId,
         pub native_amount: Balance,
@@ -50,227 +58,1934 @@ pub mod ido {
         pub nonce: u128,
     }
 
+    #[ink(event)]
+    pub struct BuyTokenWithToken {
+        pub payer: AccountId,
+        #[ink(topic)]
+        pub buyer: AccountId,
+        #[ink(topic)]
+        pub payment_token: AccountId,
+        pub payment_amount: Balance,
+        pub new_ido_token_amount: Balance,
+        pub nonce: u128,
+    }
+
+    #[ink(event)]
+    pub struct SetTokenPrice {
+        #[ink(topic)]
+        pub payment_token: AccountId,
+        pub price: types::Price,
+    }
+
     #[ink(event)]
     pub struct ClaimToken {
         #[ink(topic)]
         pub buyer: AccountId,
+        pub recipient: AccountId,
         pub new_ido_token_amount: Balance,
         pub nonce: u128,
     }
 
-    #[ink(storage)]
-    #[derive(Default, Storage)]
-    pub struct IdoContract {
-        #[storage_field]
-        ido: types::Data,
-        #[storage_field]
-        access: access_control::Data,
-        is_initialized: bool,
-    }
+    #[ink(event)]
+    pub struct SetVesting {
+        pub vesting: types::VestingConfig,
+    }
+
+    #[ink(event)]
+    pub struct SetPhases {
+        pub phases: Vec<types::Phase>,
+    }
+
+    #[ink(event)]
+    pub struct PhaseChanged {
+        #[ink(topic)]
+        pub index: u32,
+        pub phase: types::Phase,
+    }
+
+    #[ink(event)]
+    pub struct SetCaps {
+        pub soft_cap: Balance,
+        pub hard_cap: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SetSaleEnd {
+        pub sale_end: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct SaleFinalized {
+        pub total_raised: Balance,
+        pub status: types::SaleStatus,
+    }
+
+    #[ink(event)]
+    pub struct Refunded {
+        #[ink(topic)]
+        pub buyer: AccountId,
+        pub payment_token: Option<AccountId>,
+        pub amount: Balance,
+        pub ido_token_amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SetPlatformFee {
+        pub platform_fee: u32,
+        #[ink(topic)]
+        pub platform_treasury: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct WithdrawRaised {
+        #[ink(topic)]
+        pub to: AccountId,
+        pub payment_token: Option<AccountId>,
+        pub amount: Balance,
+        pub fee: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SetSignatureMode {
+        pub mode: types::SignatureMode,
+        pub eth_signer: [u8; 20],
+    }
+
+    #[ink(event)]
+    pub struct SignerAdded {
+        pub signer: types::SignerId,
+    }
+
+    #[ink(event)]
+    pub struct SignerRemoved {
+        pub signer: types::SignerId,
+    }
+
+    #[ink(event)]
+    pub struct SignerThresholdChanged {
+        pub threshold: u32,
+    }
+
+    #[ink(event)]
+    pub struct SignerProposed {
+        #[ink(topic)]
+        pub new_signer: AccountId,
+        pub activate_at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct SignerChanged {
+        #[ink(topic)]
+        pub old_signer: AccountId,
+        #[ink(topic)]
+        pub new_signer: AccountId,
+        /// the old signer stays valid until this time, 0 when it was revoked immediately
+        pub grace_until: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct EthSignerChanged {
+        #[ink(topic)]
+        pub old_signer: [u8; 20],
+        #[ink(topic)]
+        pub new_signer: [u8; 20],
+    }
+
+    #[ink(event)]
+    pub struct SetAllocationMode {
+        pub mode: types::AllocationMode,
+    }
+
+    #[ink(event)]
+    pub struct SetMerkleRoot {
+        pub merkle_root: [u8; 32],
+    }
+
+    #[ink(event)]
+    pub struct AllowlistPurchase {
+        #[ink(topic)]
+        pub buyer: AccountId,
+        pub payment_token: Option<AccountId>,
+        pub payment_amount: Balance,
+        pub new_ido_token_amount: Balance,
+        pub spent: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SetTierAllocations {
+        #[ink(topic)]
+        pub staking_contract: AccountId,
+        pub tier_allocations: Vec<Balance>,
+    }
+
+    #[ink(event)]
+    pub struct SetSaleMode {
+        pub sale_mode: types::SaleMode,
+    }
+
+    #[ink(event)]
+    pub struct Committed {
+        #[ink(topic)]
+        pub buyer: AccountId,
+        pub payment_token: Option<AccountId>,
+        pub amount: Balance,
+        pub committed: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Settled {
+        #[ink(topic)]
+        pub buyer: AccountId,
+        pub committed: Balance,
+        pub ido_token_amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SetDutchAuction {
+        pub auction: types::DutchAuction,
+    }
+
+    #[ink(event)]
+    pub struct AuctionRebate {
+        #[ink(topic)]
+        pub buyer: AccountId,
+        pub clearing_price: types::Price,
+        pub rebate: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SaleCancelled {
+        pub total_raised: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SaleTokensRecovered {
+        #[ink(topic)]
+        pub to: AccountId,
+        pub amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct UnsoldSwept {
+        #[ink(topic)]
+        pub to: AccountId,
+        pub amount: Balance,
+        pub owed: Balance,
+    }
+
+    #[ink(event)]
+    pub struct PoolFunded {
+        #[ink(topic)]
+        pub funder: AccountId,
+        pub amount: Balance,
+        pub balance: Balance,
+    }
+
+    #[ink(event)]
+    pub struct AllocationEpochBumped {
+        pub epoch: u32,
+    }
+
+    #[ink(event)]
+    pub struct GrantPurchase {
+        pub payer: AccountId,
+        #[ink(topic)]
+        pub buyer: AccountId,
+        pub payment_token: Option<AccountId>,
+        pub payment_amount: Balance,
+        pub new_ido_token_amount: Balance,
+        pub spent: Balance,
+    }
+
+    #[ink(event)]
+    pub struct NoncesInvalidated {
+        #[ink(topic)]
+        pub account: AccountId,
+        pub action: types::SignedAction,
+        pub next_nonce: u128,
+    }
+
+    #[ink(storage)]
+    #[derive(Default, Storage)]
+    pub struct IdoContract {
+        #[storage_field]
+        ido: types::Data,
+        #[storage_field]
+        access: access_control::Data,
+        #[storage_field]
+        pausable: pausable::Data,
+        is_initialized: bool,
+    }
+
+    impl traits::Internal for IdoContract {
+        fn _recover_signer(&self, message_hash: [u8; 32], signature: [u8; 65]) -> Option<types::SignerId> {
+            match self.ido.signature_mode {
+                types::SignatureMode::Substrate => {
+                    let output = self.env().ecdsa_recover(&signature, &message_hash).ok()?;
+                    let mut signature_account_id = <hash::Blake2x256 as hash::HashOutput>::Type::default();
+                    ink::env::hash_encoded::<hash::Blake2x256, _>(&output, &mut signature_account_id);
+                    Some(types::SignerId::Substrate(AccountId::from(signature_account_id)))
+                }
+                types::SignatureMode::Ethereum => {
+                    let eth_hash = helpers::eth_message_hash(&message_hash);
+                    let output = self.env().ecdsa_recover(&signature, &eth_hash).ok()?;
+                    self.env().ecdsa_to_eth_address(&output).ok().map(types::SignerId::Ethereum)
+                }
+            }
+        }
+
+        fn _is_signer(&self, signer: &types::SignerId) -> bool {
+            self.get_signers().contains(signer)
+        }
+
+        fn _verify_signatures(&self, message_hash: [u8; 32], signatures: &[[u8; 65]]) -> bool {
+            let mut approvals: Vec<types::SignerId> = Vec::new();
+            for signature in signatures {
+                if let Some(signer) = self._recover_signer(message_hash, *signature) {
+                    if self._is_signer(&signer) && !approvals.contains(&signer) {
+                        approvals.push(signer);
+                    }
+                }
+            }
+            approvals.len() as u32 >= self.ido.signer_threshold
+        }
+
+        fn _emit_buy_with_native_event(&self, _payer: AccountId, _buyer: AccountId, _native_amount: Balance, _ido_token_amount: Balance, _nonce: u128) {
+            self.env().emit_event(BuyTokenWithNative {
+                payer: _payer,
+                buyer: _buyer,
+                native_amount: _native_amount,
+                new_ido_token_amount: _ido_token_amount,
+                nonce: _nonce,
+            });
+        }
+
+        fn _emit_buy_with_token_event(&self, _payer: AccountId, _buyer: AccountId, _payment_token: AccountId, _payment_amount: Balance, _ido_token_amount: Balance, _nonce: u128) {
+            self.env().emit_event(BuyTokenWithToken {
+                payer: _payer,
+                buyer: _buyer,
+                payment_token: _payment_token,
+                payment_amount: _payment_amount,
+                new_ido_token_amount: _ido_token_amount,
+                nonce: _nonce,
+            });
+        }
+
+        fn _emit_set_token_price_event(&self, _payment_token: AccountId, _price: types::Price) {
+            self.env().emit_event(SetTokenPrice {
+                payment_token: _payment_token,
+                price: _price,
+            });
+        }
+
+        fn _emit_claim_token_event(&self, _buyer: AccountId, _recipient: AccountId, _ido_token_amount: Balance, _nonce: u128) {
+            self.env().emit_event(ClaimToken {
+                buyer: _buyer,
+                recipient: _recipient,
+                new_ido_token_amount: _ido_token_amount,
+                nonce: _nonce,
+            });
+        }
+
+        fn _emit_set_vesting_event(&self, _vesting: types::VestingConfig) {
+            self.env().emit_event(SetVesting {
+                vesting: _vesting,
+            });
+        }
+
+        fn _emit_set_phases_event(&self, _phases: Vec<types::Phase>) {
+            self.env().emit_event(SetPhases {
+                phases: _phases,
+            });
+        }
+
+        fn _emit_phase_changed_event(&self, _index: u32, _phase: types::Phase) {
+            self.env().emit_event(PhaseChanged {
+                index: _index,
+                phase: _phase,
+            });
+        }
+
+        fn _emit_set_caps_event(&self, _soft_cap: Balance, _hard_cap: Balance) {
+            self.env().emit_event(SetCaps {
+                soft_cap: _soft_cap,
+                hard_cap: _hard_cap,
+            });
+        }
+
+        fn _emit_set_sale_end_event(&self, _sale_end: Timestamp) {
+            self.env().emit_event(SetSaleEnd {
+                sale_end: _sale_end,
+            });
+        }
+
+        fn _emit_sale_finalized_event(&self, _total_raised: Balance, _status: types::SaleStatus) {
+            self.env().emit_event(SaleFinalized {
+                total_raised: _total_raised,
+                status: _status,
+            });
+        }
+
+        fn _emit_refunded_event(&self, _buyer: AccountId, _payment_token: Option<AccountId>, _amount: Balance, _ido_token_amount: Balance) {
+            self.env().emit_event(Refunded {
+                buyer: _buyer,
+                payment_token: _payment_token,
+                amount: _amount,
+                ido_token_amount: _ido_token_amount,
+            });
+        }
+
+        fn _emit_set_platform_fee_event(&self, _platform_fee: u32, _platform_treasury: AccountId) {
+            self.env().emit_event(SetPlatformFee {
+                platform_fee: _platform_fee,
+                platform_treasury: _platform_treasury,
+            });
+        }
+
+        fn _emit_withdraw_raised_event(&self, _to: AccountId, _payment_token: Option<AccountId>, _amount: Balance, _fee: Balance) {
+            self.env().emit_event(WithdrawRaised {
+                to: _to,
+                payment_token: _payment_token,
+                amount: _amount,
+                fee: _fee,
+            });
+        }
+
+        fn _emit_set_signature_mode_event(&self, _mode: types::SignatureMode, _eth_signer: [u8; 20]) {
+            self.env().emit_event(SetSignatureMode {
+                mode: _mode,
+                eth_signer: _eth_signer,
+            });
+        }
+
+        fn _emit_signer_added_event(&self, _signer: types::SignerId) {
+            self.env().emit_event(SignerAdded {
+                signer: _signer,
+            });
+        }
+
+        fn _emit_signer_removed_event(&self, _signer: types::SignerId) {
+            self.env().emit_event(SignerRemoved {
+                signer: _signer,
+            });
+        }
+
+        fn _emit_signer_threshold_changed_event(&self, _threshold: u32) {
+            self.env().emit_event(SignerThresholdChanged {
+                threshold: _threshold,
+            });
+        }
+
+        fn _emit_signer_proposed_event(&self, _new_signer: AccountId, _activate_at: Timestamp) {
+            self.env().emit_event(SignerProposed {
+                new_signer: _new_signer,
+                activate_at: _activate_at,
+            });
+        }
+
+        fn _emit_signer_changed_event(&self, _old_signer: AccountId, _new_signer: AccountId, _grace_until: Timestamp) {
+            self.env().emit_event(SignerChanged {
+                old_signer: _old_signer,
+                new_signer: _new_signer,
+                grace_until: _grace_until,
+            });
+        }
+
+        fn _emit_eth_signer_changed_event(&self, _old_signer: [u8; 20], _new_signer: [u8; 20]) {
+            self.env().emit_event(EthSignerChanged {
+                old_signer: _old_signer,
+                new_signer: _new_signer,
+            });
+        }
+
+        fn _emit_set_allocation_mode_event(&self, _mode: types::AllocationMode) {
+            self.env().emit_event(SetAllocationMode {
+                mode: _mode,
+            });
+        }
+
+        fn _emit_set_merkle_root_event(&self, _merkle_root: [u8; 32]) {
+            self.env().emit_event(SetMerkleRoot {
+                merkle_root: _merkle_root,
+            });
+        }
+
+        fn _emit_allowlist_purchase_event(&self, _buyer: AccountId, _payment_token: Option<AccountId>, _payment_amount: Balance, _ido_token_amount: Balance, _spent: Balance) {
+            self.env().emit_event(AllowlistPurchase {
+                buyer: _buyer,
+                payment_token: _payment_token,
+                payment_amount: _payment_amount,
+                new_ido_token_amount: _ido_token_amount,
+                spent: _spent,
+            });
+        }
+
+        fn _emit_set_tier_allocations_event(&self, _staking_contract: AccountId, _tier_allocations: Vec<Balance>) {
+            self.env().emit_event(SetTierAllocations {
+                staking_contract: _staking_contract,
+                tier_allocations: _tier_allocations,
+            });
+        }
+
+        fn _emit_set_sale_mode_event(&self, _sale_mode: types::SaleMode) {
+            self.env().emit_event(SetSaleMode {
+                sale_mode: _sale_mode,
+            });
+        }
+
+        fn _emit_committed_event(&self, _buyer: AccountId, _payment_token: Option<AccountId>, _amount: Balance, _committed: Balance) {
+            self.env().emit_event(Committed {
+                buyer: _buyer,
+                payment_token: _payment_token,
+                amount: _amount,
+                committed: _committed,
+            });
+        }
+
+        fn _emit_settled_event(&self, _buyer: AccountId, _committed: Balance, _ido_token_amount: Balance) {
+            self.env().emit_event(Settled {
+                buyer: _buyer,
+                committed: _committed,
+                ido_token_amount: _ido_token_amount,
+            });
+        }
+
+        fn _emit_set_dutch_auction_event(&self, _auction: types::DutchAuction) {
+            self.env().emit_event(SetDutchAuction {
+                auction: _auction,
+            });
+        }
+
+        fn _emit_auction_rebate_event(&self, _buyer: AccountId, _clearing_price: types::Price, _rebate: Balance) {
+            self.env().emit_event(AuctionRebate {
+                buyer: _buyer,
+                clearing_price: _clearing_price,
+                rebate: _rebate,
+            });
+        }
+
+        fn _emit_sale_cancelled_event(&self, _total_raised: Balance) {
+            self.env().emit_event(SaleCancelled {
+                total_raised: _total_raised,
+            });
+        }
+
+        fn _emit_sale_tokens_recovered_event(&self, _to: AccountId, _amount: Balance) {
+            self.env().emit_event(SaleTokensRecovered {
+                to: _to,
+                amount: _amount,
+            });
+        }
+
+        fn _emit_unsold_swept_event(&self, _to: AccountId, _amount: Balance, _owed: Balance) {
+            self.env().emit_event(UnsoldSwept {
+                to: _to,
+                amount: _amount,
+                owed: _owed,
+            });
+        }
+
+        fn _emit_pool_funded_event(&self, _funder: AccountId, _amount: Balance, _balance: Balance) {
+            self.env().emit_event(PoolFunded {
+                funder: _funder,
+                amount: _amount,
+                balance: _balance,
+            });
+        }
+
+        fn _emit_allocation_epoch_bumped_event(&self, _epoch: u32) {
+            self.env().emit_event(AllocationEpochBumped {
+                epoch: _epoch,
+            });
+        }
+
+        fn _emit_grant_purchase_event(&self, _payer: AccountId, _buyer: AccountId, _payment_token: Option<AccountId>, _payment_amount: Balance, _ido_token_amount: Balance, _spent: Balance) {
+            self.env().emit_event(GrantPurchase {
+                payer: _payer,
+                buyer: _buyer,
+                payment_token: _payment_token,
+                payment_amount: _payment_amount,
+                new_ido_token_amount: _ido_token_amount,
+                spent: _spent,
+            });
+        }
+
+        fn _emit_nonces_invalidated_event(&self, _account: AccountId, _action: types::SignedAction, _next_nonce: u128) {
+            self.env().emit_event(NoncesInvalidated {
+                account: _account,
+                action: _action,
+                next_nonce: _next_nonce,
+            });
+        }
+
+        fn _emit_init_ido_contract_event(&self, _ido_token: AccountId, _price: types::Price, _token_decimals: u8, _signer: AccountId, _max_issue_ido_amount: u128) {
+            self.env().emit_event(InitIdoContract {
+                ido_token: _ido_token,
+                price: _price,
+                token_decimals: _token_decimals,
+                signer: _signer,
+                max_issue_ido_amount: _max_issue_ido_amount,
+            });
+        }
+    }
+
+
+    impl traits::Ido for IdoContract {
+        /// this function is initialised function, will init the contract properties,
+        /// `_price` is the cost of one ido token in native currency and the ido token decimals are read from its metadata,
+        /// only the deployer can call this function
+        #[ink(message)]
+        #[modifiers(only_role(INITIALIZER))]
+        fn init_ido(&mut self, _ido_token: AccountId, _signer: AccountId, _price: types::Price, _max_issue_ido_amount: u128) -> Result<(), IDOError> {
+            ensure!(self.is_initialized == false, IDOError::Initialized);
+            ensure!(_ido_token != ZERO_ADDRESS.into(), IDOError::ZeroTokenAddress);
+            let token_decimals = self._token_decimals(_ido_token)?;
+            self._init_ido(_ido_token, _signer, _price, token_decimals, _max_issue_ido_amount)
+        }
+
+        /// get ido token
+        #[ink(message)]
+        fn get_ido_token(&self) -> AccountId {
+            self.ido.ido_token
+        }
+
+        /// function to get the next valid nonce of the account for the signed action
+        #[ink(message)]
+        fn get_nonce(&self, account: AccountId, action: types::SignedAction) -> u128 {
+            self.ido.account_nonce.get(&(account, action)).unwrap_or(0)
+        }
+
+        /// function to revoke every signature of the caller for the action with a nonce up to `nonce`
+        #[ink(message)]
+        fn invalidate_nonces_up_to(&mut self, action: types::SignedAction, nonce: u128) -> Result<(), IDOError> {
+            let caller = self.env().caller();
+            ensure!(nonce >= self.get_nonce(caller, action), IDOError::InvalidNonce(nonce.to_string()));
+            let next_nonce = nonce.checked_add(1).ok_or(IDOError::InvalidNonce(nonce.to_string()))?;
+            self.ido.account_nonce.insert(&(caller, action), &next_nonce);
+            self._emit_nonces_invalidated_event(caller, action, next_nonce);
+            Ok(())
+        }
+
+        /// function to buy ido token with native for `beneficiary`, reverts when the beneficiary would receive less than `min_ido_amount_out`
+        #[ink(message, payable)]
+        #[modifiers(when_not_paused)]
+        fn buy_ido_with_native(&mut self, beneficiary: AccountId, deadline: Timestamp, nonce: u128, min_ido_amount_out: Balance, signatures: Vec<[u8; 65]>) -> Result<(), IDOError> {
+            ensure!(
+                deadline >= self.env().block_timestamp(),
+                IDOError::Expired
+            );
+            ensure!(
+                nonce == self.get_nonce(beneficiary, types::SignedAction::BuyWithNative),
+                IDOError::InvalidNonce(nonce.to_string())
+            );
+            ensure!(self.ido.allocation_mode == types::AllocationMode::Signature, IDOError::WrongAllocationMode);
+            let payer = self.env().caller();
+            // legacy messages only carry the caller
+            ensure!(!self.ido.legacy_messages || beneficiary == payer, IDOError::LegacyMessageUnsupported);
+
+            let phase_index = self._open_phase()?;
+
+            self.ido.account_nonce.insert(&(beneficiary, types::SignedAction::BuyWithNative), &(nonce + 1));
+
+            let received_value = Self::env().transferred_value();
+
+            let message_hash = self.gen_hash_for_buy_token(payer, beneficiary, deadline, nonce, received_value);
+
+            // verify signature
+            let is_ok = self._verify_signatures(message_hash, &signatures);
+
+            if !is_ok {
+                return Err(IDOError::InvalidSignature);
+            }
+
+            let new_balances = self._buy(beneficiary, phase_index, None, received_value, min_ido_amount_out)?;
+
+            // emit event
+            self._emit_buy_with_native_event(payer, beneficiary, received_value, new_balances, nonce);
+            Ok(())
+        }
+
+        /// function to buy ido token with an accepted PSP22 payment token, the buyer must approve `amount` for the pool first
+        #[ink(message)]
+        #[modifiers(when_not_paused)]
+        fn buy_ido_with_token(&mut self, payment_token: AccountId, amount: Balance, beneficiary: AccountId, deadline: Timestamp, nonce: u128, min_ido_amount_out: Balance, signatures: Vec<[u8; 65]>) -> Result<(), IDOError> {
+            ensure!(
+                deadline >= self.env().block_timestamp(),
+                IDOError::Expired
+            );
+            ensure!(
+                nonce == self.get_nonce(beneficiary, types::SignedAction::BuyWithToken),
+                IDOError::InvalidNonce(nonce.to_string())
+            );
+            ensure!(self.ido.allocation_mode == types::AllocationMode::Signature, IDOError::WrongAllocationMode);
+            let payer = self.env().caller();
+            // legacy messages only carry the caller
+            ensure!(!self.ido.legacy_messages || beneficiary == payer, IDOError::LegacyMessageUnsupported);
+
+            let phase_index = self._open_phase()?;
+
+            self.ido.account_nonce.insert(&(beneficiary, types::SignedAction::BuyWithToken), &(nonce + 1));
+
+            let message_hash = self.gen_hash_for_buy_token_with_token(payer, beneficiary, payment_token, amount, deadline, nonce);
+
+            // verify signature
+            let is_ok = self._verify_signatures(message_hash, &signatures);
+
+            if !is_ok {
+                return Err(IDOError::InvalidSignature);
+            }
+
+            let new_balances = self._buy(beneficiary, phase_index, Some(payment_token), amount, min_ido_amount_out)?;
+
+            // emit event
+            self._emit_buy_with_token_event(payer, beneficiary, payment_token, amount, new_balances, nonce);
+            Ok(())
+        }
+
+        /// function to claim ido token of the caller to `recipient`
+        #[ink(message)]
+        #[modifiers(when_not_paused)]
+        fn claim_ido_token(&mut self, recipient: AccountId, deadline: Timestamp, nonce: u128, amount: Balance, signatures: Vec<[u8; 65]>) -> Result<(), IDOError> {
+            ensure!(
+                deadline >= self.env().block_timestamp(),
+                IDOError::Expired
+            );
+
+            ensure!(
+                nonce == self.get_nonce(self.env().caller(), types::SignedAction::Claim),
+                IDOError::InvalidNonce(nonce.to_string())
+            );
+
+            ensure!(
+                self.ido.status != types::SaleStatus::Refunding && self.ido.status != types::SaleStatus::Cancelled,
+                IDOError::SaleClosed
+            );
+            // with a soft cap the sale may still fail, tokens are released only after finalisation
+            ensure!(
+                self.ido.soft_cap == 0 || self.ido.status == types::SaleStatus::Finalized,
+                IDOError::SaleNotFinalized
+            );
+
+            let caller = Self::env().caller();
+            // legacy messages only carry the caller
+            ensure!(!self.ido.legacy_messages || recipient == caller, IDOError::LegacyMessageUnsupported);
+
+            self.ido.account_nonce.insert(&(caller, types::SignedAction::Claim), &(nonce + 1));
+
+            // ensure the user has enough collateral assets
+            if PSP22Ref::balance_of(&self.ido.ido_token, self.env().account_id()) < amount {
+                return Err(IDOError::InsufficientBalance)
+            }
+            let message_hash = self.gen_hash_for_claim_token(caller, recipient, deadline, nonce, amount);
+
+            // verify signature
+            let is_ok = self._verify_signatures(message_hash, &signatures);
+
+            if !is_ok {
+                return Err(IDOError::InvalidSignature);
+            }
+
+            // the signer can only release what is already vested
+            ensure!(amount <= self.claimable_of(caller), IDOError::ExceedsClaimable);
+
+            let old_balances = self.ido.user_ido_balances.get(&self.env().caller()).unwrap_or(0);
+            let new_balances = old_balances.checked_sub(amount).ok_or(IDOError::InsufficientBalance)?;
+            self.ido.user_ido_balances.insert(self.env().caller(), &new_balances);
+
+            let claimed = self.ido.user_claimed.get(&caller).unwrap_or(0);
+            self.ido.user_claimed.insert(&caller, &(claimed + amount));
+            self.ido.total_claimed += amount;
+
+            let result = helpers::safe_transfer(self.ido.ido_token, recipient, amount);
+            // check result
+            if result.is_err() {
+                return Err(IDOError::SafeTransferError);
+            }
+
+            self._emit_claim_token_event(caller, recipient, new_balances, nonce);
+            Ok(())
+        }
+
+        /// function to set price of ido token, only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        fn admin_set_price(&mut self, new_price: types::Price) -> Result<(), IDOError> {
+            // allocations of a finished sale are computed with the pool price, so it is fixed from then on
+            ensure!(self.ido.status == types::SaleStatus::Active, IDOError::SaleClosed);
+            ensure!(new_price.is_valid(), IDOError::InvalidPrice);
+            if self.ido.sale_mode == types::SaleMode::Overflow {
+                self._ensure_overflow_cap(self.ido.hard_cap, new_price)?;
+            }
+            self.ido.price = new_price;
+            Ok(())
+        }
+
+        /// function to get price of ido token
+        #[ink(message)]
+        fn get_price(&self) -> types::Price {
+            self.ido.price
+        }
+
+        /// function to set price of ido token in a PSP22 payment token, a zero price stops accepting the token,
+        /// only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        fn admin_set_token_price(&mut self, payment_token: AccountId, new_price: types::Price) -> Result<(), IDOError> {
+            ensure!(payment_token != self.ido.ido_token, IDOError::UnsupportedPaymentToken);
+            ensure!(new_price.numerator == 0 || new_price.is_valid(), IDOError::InvalidPrice);
+            let decimals = match self.ido.payment_token_decimals.get(&payment_token) {
+                Some(decimals) => decimals,
+                None => self._token_decimals(payment_token)?,
+            };
+            self._set_token_price(payment_token, new_price, decimals);
+            Ok(())
+        }
+
+        /// function to get price of ido token in a PSP22 payment token
+        #[ink(message)]
+        fn get_token_price(&self, payment_token: AccountId) -> types::Price {
+            self.ido.token_prices.get(&payment_token).unwrap_or_default()
+        }
+    }
+
+    impl access_control::AccessControl for IdoContract {}
+
+    impl Pausable for IdoContract {}
+
+
+    impl IdoContract {
+        /// constructor of IDO contract, the sale is configured afterwards by the deployer with `init_ido`
+        #[ink(constructor)]
+        pub fn new(owner: AccountId) -> Self {
+            let mut instance = Self::default();
+            instance._init_with_admin(owner);
+            instance._setup_role(INITIALIZER, Self::env().caller());
+            instance.is_initialized = false;
+            instance
+        }
+
+        /// constructor of IDO contract taking the full sale configuration, so the sale cannot be initialised by anyone else
+        #[ink(constructor)]
+        pub fn new_with_config(owner: AccountId, ido_token: AccountId, signer: AccountId, price: types::Price, max_issue_ido_amount: u128) -> Result<Self, IDOError> {
+            let mut instance = Self::default();
+            instance._init_with_admin(owner);
+            ensure!(ido_token != ZERO_ADDRESS.into(), IDOError::ZeroTokenAddress);
+            let token_decimals = instance._token_decimals(ido_token)?;
+            instance._init_ido(ido_token, signer, price, token_decimals, max_issue_ido_amount)?;
+            Ok(instance)
+        }
+
+        /// function to get balance of ido token
+        #[ink(message)]
+        pub fn get_ido_token_balance(&self, account: AccountId) -> u128 {
+            return self.ido.user_ido_balances.get(&account).unwrap_or(0 as u128);
+        }
+
+        /// function to set the unlock schedule of purchased ido tokens, the schedule is fixed once anything is bought,
+        /// only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn admin_set_vesting(&mut self, vesting: types::VestingConfig) -> Result<(), IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Active, IDOError::SaleClosed);
+            ensure!(self.ido.issued_ido_amount == 0 && self.ido.total_raised == 0, IDOError::VestingLocked);
+            ensure!(vesting.is_valid(), IDOError::InvalidVestingConfig);
+            self.ido.vesting = vesting;
+            self._emit_set_vesting_event(vesting);
+            Ok(())
+        }
+
+        /// function to get the unlock schedule of purchased ido tokens
+        #[ink(message)]
+        pub fn get_vesting(&self) -> types::VestingConfig {
+            self.ido.vesting
+        }
+
+        /// function to get the amount of ido token already claimed by the account
+        #[ink(message)]
+        pub fn claimed_of(&self, account: AccountId) -> Balance {
+            self.ido.user_claimed.get(&account).unwrap_or(0)
+        }
+
+        /// function to get the amount of ido token the account can claim now, vested amount minus already claimed amount
+        #[ink(message)]
+        pub fn claimable_of(&self, account: AccountId) -> Balance {
+            let balance = self.ido.user_ido_balances.get(&account).unwrap_or(0);
+            let claimed = self.claimed_of(account);
+            let vested = self.ido.vesting.vested_amount(balance + claimed, self.env().block_timestamp());
+            vested.saturating_sub(claimed).min(balance)
+        }
+
+        /// function to set the sale phases, phases must be sorted by time and must not overlap, they are fixed once
+        /// ido token is issued because phase caps are tracked by index, only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn admin_set_phases(&mut self, phases: Vec<types::Phase>) -> Result<(), IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Active, IDOError::SaleClosed);
+            ensure!(self.ido.issued_ido_amount == 0, IDOError::PhasesLocked);
+            ensure!(types::is_valid_phases(&phases), IDOError::InvalidPhaseConfig);
+            self.ido.phases = phases.clone();
+            self.ido.last_phase = None;
+            self._emit_set_phases_event(phases);
+            Ok(())
+        }
+
+        /// function to get the sale phases
+        #[ink(message)]
+        pub fn get_phases(&self) -> Vec<types::Phase> {
+            self.ido.phases.clone()
+        }
+
+        /// function to get the phase open at the current block with its index, `None` when no phase is open
+        #[ink(message)]
+        pub fn current_phase(&self) -> Option<(u32, types::Phase)> {
+            let now = self.env().block_timestamp();
+            self.ido.phases
+                .iter()
+                .position(|phase| phase.is_open(now))
+                .map(|index| (index as u32, self.ido.phases[index]))
+        }
+
+        /// function to set when a sale without phases stops taking purchases and can be finalized, 0 removes the end,
+        /// only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn admin_set_sale_end(&mut self, sale_end: Timestamp) -> Result<(), IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Active, IDOError::SaleClosed);
+            self.ido.sale_end = sale_end;
+            self._emit_set_sale_end_event(sale_end);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn get_sale_end(&self) -> Timestamp {
+            self.ido.sale_end
+        }
+
+        /// a sale without phases ends at `sale_end` when one is set
+        fn _sale_end_passed(&self, now: Timestamp) -> bool {
+            self.ido.sale_end > 0 && now >= self.ido.sale_end
+        }
+
+        /// function to get the amount of ido token issued in the phase
+        #[ink(message)]
+        pub fn get_phase_issued(&self, index: u32) -> Balance {
+            self.ido.phase_issued.get(&index).unwrap_or(0)
+        }
+
+        /// function to set soft cap and hard cap in native currency, 0 disables the cap, only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn admin_set_caps(&mut self, soft_cap: Balance, hard_cap: Balance) -> Result<(), IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Active, IDOError::SaleClosed);
+            ensure!(hard_cap == 0 || soft_cap <= hard_cap, IDOError::InvalidCaps);
+            if self.ido.sale_mode == types::SaleMode::Overflow {
+                self._ensure_overflow_cap(hard_cap, self.ido.price)?;
+            }
+            self.ido.soft_cap = soft_cap;
+            self.ido.hard_cap = hard_cap;
+            self._emit_set_caps_event(soft_cap, hard_cap);
+            Ok(())
+        }
+
+        /// function to get soft cap and hard cap
+        #[ink(message)]
+        pub fn get_caps(&self) -> (Balance, Balance) {
+            (self.ido.soft_cap, self.ido.hard_cap)
+        }
+
+        /// function to get total raised value in native currency
+        #[ink(message)]
+        pub fn get_total_raised(&self) -> Balance {
+            self.ido.total_raised
+        }
+
+        #[ink(message)]
+        pub fn get_sale_status(&self) -> types::SaleStatus {
+            self.ido.status
+        }
+
+        /// function to get the contribution of the account in the payment token, `None` is the native currency
+        #[ink(message)]
+        pub fn contribution_of(&self, account: AccountId, payment_token: Option<AccountId>) -> Balance {
+            self.ido.user_contributions.get(&(account, payment_token)).unwrap_or(0)
+        }
+
+        /// function to close the sale after the last phase or the sale end passed or the hard cap is reached,
+        /// the pool switches to refund mode when the soft cap is missed
+        #[ink(message)]
+        pub fn finalize(&mut self) -> Result<(), IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Active, IDOError::SaleClosed);
+            let now = self.env().block_timestamp();
+            // an overflow sale keeps taking commitments above the hard cap until the last phase ends
+            let hard_cap_reached = self.ido.sale_mode != types::SaleMode::Overflow
+                && self.ido.hard_cap > 0
+                && self.ido.total_raised >= self.ido.hard_cap;
+            let ended = match self.ido.phases.last() {
+                Some(phase) => now >= phase.end,
+                None => self._sale_end_passed(now)
+                    || (self.ido.sale_mode == types::SaleMode::DutchAuction && self.ido.auction.is_valid() && now >= self.ido.auction.end),
+            };
+            ensure!(hard_cap_reached || ended, IDOError::SaleNotEnded);
+
+            self.ido.status = if self.ido.total_raised < self.ido.soft_cap {
+                types::SaleStatus::Refunding
+            } else {
+                types::SaleStatus::Finalized
+            };
+            self._emit_sale_finalized_event(self.ido.total_raised, self.ido.status);
+            Ok(())
+        }
+
+        /// function to take back all contributions of the caller when the sale missed the soft cap or was cancelled
+        #[ink(message)]
+        pub fn refund(&mut self) -> Result<(), IDOError> {
+            ensure!(
+                self.ido.status == types::SaleStatus::Refunding || self.ido.status == types::SaleStatus::Cancelled,
+                IDOError::NotRefunding
+            );
+            let caller = self.env().caller();
+
+            let ido_amount = self.ido.user_ido_balances.get(&caller).unwrap_or(0);
+            self.ido.user_ido_balances.insert(&caller, &0);
+            self.ido.issued_ido_amount = self.ido.issued_ido_amount.saturating_sub(ido_amount);
+            self.ido.committed.insert(&caller, &0);
+
+            // claimed ido token stays with the buyer, so only the share paid for the unclaimed part is returned
+            let claimed = self.claimed_of(caller);
+            let unclaimed_share = |amount: Balance| -> Result<Balance, IDOError> {
+                if claimed == 0 {
+                    return Ok(amount);
+                }
+                helpers::mul_div(amount, ido_amount, ido_amount + claimed, false).ok_or(IDOError::ArithmeticOverflow)
+            };
+            let contributed = self.contributed_value_of(caller);
+            let refunded_value = unclaimed_share(contributed)?;
+            self.ido.contributed_value.insert(&caller, &(contributed - refunded_value));
+            self.ido.total_raised = self.ido.total_raised.saturating_sub(refunded_value);
+
+            // the returned ido token is reported with the first refunded currency only
+            let mut refunded_ido_amount = ido_amount;
+            let mut refunded = false;
+            for payment_token in self._currencies() {
+                let contribution = self.contribution_of(caller, payment_token);
+                let amount = unclaimed_share(contribution)?;
+                if amount == 0 {
+                    continue;
+                }
+                self.ido.user_contributions.insert(&(caller, payment_token), &(contribution - amount));
+                let raised = self.ido.raised.get(&payment_token).unwrap_or(0);
+                self.ido.raised.insert(&payment_token, &raised.saturating_sub(amount));
+                self._send(payment_token, caller, amount)?;
+                self._emit_refunded_event(caller, payment_token, amount, refunded_ido_amount);
+                refunded_ido_amount = 0;
+                refunded = true;
+            }
+            ensure!(refunded, IDOError::NothingToRefund);
+            Ok(())
+        }
+
+        /// function to stop buys and claims, only pauser can call this function
+        #[ink(message)]
+        #[modifiers(only_role(PAUSER))]
+        pub fn pause(&mut self) -> Result<(), IDOError> {
+            self._pause()
+        }
+
+        /// function to resume buys and claims, only pauser can call this function
+        #[ink(message)]
+        #[modifiers(only_role(PAUSER))]
+        pub fn unpause(&mut self) -> Result<(), IDOError> {
+            self._unpause()
+        }
+
+        /// function to stop the sale for good, buyers can take back their contributions with `refund`
+        /// and the project its ido token with `recover_sale_tokens`, only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(DEFAULT_ADMIN_ROLE))]
+        pub fn cancel_sale(&mut self) -> Result<(), IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Active, IDOError::SaleClosed);
+            self.ido.status = types::SaleStatus::Cancelled;
+            self._emit_sale_cancelled_event(self.ido.total_raised);
+            Ok(())
+        }
+
+        /// function to send all ido token deposited in a cancelled sale back to the project, only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn recover_sale_tokens(&mut self, to: AccountId) -> Result<(), IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Cancelled, IDOError::SaleNotCancelled);
+            let amount = PSP22Ref::balance_of(&self.ido.ido_token, self.env().account_id());
+            ensure!(amount > 0, IDOError::NothingToWithdraw);
+            helpers::safe_transfer(self.ido.ido_token, to, amount).map_err(|_| IDOError::SafeTransferError)?;
+            self._emit_sale_tokens_recovered_event(to, amount);
+            Ok(())
+        }
+
+        /// function to deposit the ido token of the sale, pulls whatever the pool is missing of `max_issue_ido_amount`
+        /// from the caller, who must approve it for the pool first, and opens the pool for purchases
+        #[ink(message)]
+        pub fn fund_pool(&mut self) -> Result<(), IDOError> {
+            ensure!(self.is_initialized, IDOError::NotInitialized);
+            ensure!(!self.ido.funded, IDOError::AlreadyFunded);
+            let caller = self.env().caller();
+            let pool = self.env().account_id();
+            let balance = PSP22Ref::balance_of(&self.ido.ido_token, pool);
+            let missing = self.ido.max_issue_ido_amount.saturating_sub(balance);
+            if missing > 0 {
+                self._pull_payment(self.ido.ido_token, missing)?;
+            }
+            self.ido.funded = true;
+            self._emit_pool_funded_event(caller, missing, balance + missing);
+            Ok(())
+        }
+
+        /// function to get whether the pool is funded, the ido token balance of the pool and the amount it needs
+        #[ink(message)]
+        pub fn funding_status(&self) -> (bool, Balance, Balance) {
+            (
+                self.ido.funded,
+                PSP22Ref::balance_of(&self.ido.ido_token, self.env().account_id()),
+                self.ido.max_issue_ido_amount,
+            )
+        }
+
+        /// function to get the sale configuration, totals, open phase, participant count and status in one call
+        #[ink(message)]
+        pub fn sale_info(&self) -> types::SaleInfo {
+            types::SaleInfo {
+                ido_token: self.ido.ido_token,
+                signer: self.ido.signer,
+                price: self.ido.price,
+                token_decimals: self.ido.token_decimals,
+                max_issue_ido_amount: self.ido.max_issue_ido_amount,
+                issued_ido_amount: self.ido.issued_ido_amount,
+                total_claimed: self.ido.total_claimed,
+                total_raised: self.ido.total_raised,
+                soft_cap: self.ido.soft_cap,
+                hard_cap: self.ido.hard_cap,
+                sale_mode: self.ido.sale_mode,
+                allocation_mode: self.ido.allocation_mode,
+                status: self.ido.status,
+                current_phase: self.current_phase(),
+                sale_end: self.ido.sale_end,
+                participants: self.participants_count(),
+                funded: self.ido.funded,
+                paused: self.paused(),
+            }
+        }
+
+        /// function to get the contribution, purchased, claimed and vested ido token and the nonces of the account in one call
+        #[ink(message)]
+        pub fn user_info(&self, account: AccountId) -> types::UserInfo {
+            let claimed = self.claimed_of(account);
+            let purchased = self.get_ido_token_balance(account) + claimed;
+            types::UserInfo {
+                contributed: self.contributed_value_of(account),
+                purchased,
+                claimed,
+                claimable: self.claimable_of(account),
+                vested: self.ido.vesting.vested_amount(purchased, self.env().block_timestamp()),
+                buy_nonce: self.get_nonce(account, types::SignedAction::BuyWithNative),
+                buy_with_token_nonce: self.get_nonce(account, types::SignedAction::BuyWithToken),
+                claim_nonce: self.get_nonce(account, types::SignedAction::Claim),
+            }
+        }
+
+        /// function to get the number of accounts that contributed to the sale
+        #[ink(message)]
+        pub fn participants_count(&self) -> u32 {
+            self.ido.participant_count
+        }
+
+        /// function to list up to `limit` participants from `offset` in order of first contribution,
+        /// as `(account, contributed, purchased, claimed)` with `contributed` in native currency
+        #[ink(message)]
+        pub fn participants(&self, offset: u32, limit: u32) -> Vec<(AccountId, Balance, Balance, Balance)> {
+            let end = offset.saturating_add(limit).min(self.ido.participant_count);
+            let mut participants = Vec::new();
+            for index in offset..end {
+                let account = match self.ido.participant_at.get(&index) {
+                    Some(account) => account,
+                    None => continue,
+                };
+                let claimed = self.claimed_of(account);
+                let purchased = self.get_ido_token_balance(account) + claimed;
+                participants.push((account, self.contributed_value_of(account), purchased, claimed));
+            }
+            participants
+        }
+
+        /// function to get the ido token still owed to buyers, purchased or allocated but not claimed yet
+        #[ink(message)]
+        pub fn owed_ido_amount(&self) -> Result<Balance, IDOError> {
+            let mut owed = self.ido.issued_ido_amount;
+            if self.ido.sale_mode == types::SaleMode::Overflow {
+                // overflow allocations are issued when each buyer settles
+                let allocated = self._ido_for_payment(None, self.ido.total_raised - self._overflow_excess(self.ido.total_raised), self.ido.price)?;
+                owed = owed.max(allocated);
+            }
+            Ok(owed.saturating_sub(self.ido.total_claimed))
+        }
+
+        /// function to send the ido token not owed to any buyer back to the project after the sale is finalized
+        /// or failed its soft cap, only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn sweep_unsold(&mut self, to: AccountId) -> Result<(), IDOError> {
+            ensure!(
+                self.ido.status == types::SaleStatus::Finalized || self.ido.status == types::SaleStatus::Refunding,
+                IDOError::SaleNotFinalized
+            );
+            let owed = self.owed_ido_amount()?;
+            let balance = PSP22Ref::balance_of(&self.ido.ido_token, self.env().account_id());
+            let amount = balance.saturating_sub(owed);
+            ensure!(amount > 0, IDOError::NothingToWithdraw);
+            helpers::safe_transfer(self.ido.ido_token, to, amount).map_err(|_| IDOError::SafeTransferError)?;
+            self._emit_unsold_swept_event(to, amount, owed);
+            Ok(())
+        }
+
+        /// function to set the platform fee in basis points and the treasury receiving it, only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(DEFAULT_ADMIN_ROLE))]
+        pub fn admin_set_platform_fee(&mut self, platform_fee: u32, platform_treasury: AccountId) -> Result<(), IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Active, IDOError::SaleClosed);
+            ensure!(platform_fee <= types::BASIS_POINTS, IDOError::InvalidPlatformFee);
+            ensure!(platform_fee == 0 || platform_treasury != ZERO_ADDRESS.into(), IDOError::InvalidPlatformFee);
+            self.ido.platform_fee = platform_fee;
+            self.ido.platform_treasury = platform_treasury;
+            self._emit_set_platform_fee_event(platform_fee, platform_treasury);
+            Ok(())
+        }
+
+        /// function to get the platform fee in basis points and the treasury receiving it
+        #[ink(message)]
+        pub fn get_platform_fee(&self) -> (u32, AccountId) {
+            (self.ido.platform_fee, self.ido.platform_treasury)
+        }
+
+        /// function to get raised, withdrawn and fee amounts of the payment token, `None` is the native currency
+        #[ink(message)]
+        pub fn raised_funds(&self, payment_token: Option<AccountId>) -> (Balance, Balance, Balance) {
+            (
+                self.ido.raised.get(&payment_token).unwrap_or(0),
+                self.ido.withdrawn.get(&payment_token).unwrap_or(0),
+                self.ido.fees.get(&payment_token).unwrap_or(0),
+            )
+        }
+
+        /// function to withdraw the raised funds of a finalized sale, the platform fee goes to the platform treasury
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn withdraw_raised(&mut self, to: AccountId) -> Result<(), IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Finalized, IDOError::SaleNotFinalized);
+
+            let mut withdrawn_any = false;
+            for payment_token in self._currencies() {
+                let (raised, withdrawn, fees) = self.raised_funds(payment_token);
+                // commitments above the hard cap of an overflow sale belong to the buyers
+                let mut available = raised - self._overflow_excess(raised) - withdrawn - fees;
+                if payment_token.is_none() {
+                    // auction rebates are paid out of the native currency raised
+                    available -= self._rebate_reserve();
+                }
+                if available == 0 {
+                    continue;
+                }
+                let fee = helpers::proportion(available, self.ido.platform_fee as u128, types::BASIS_POINTS as u128);
+                let amount = available - fee;
+                self.ido.withdrawn.insert(&payment_token, &(withdrawn + amount));
+                self.ido.fees.insert(&payment_token, &(fees + fee));
+
+                self._send(payment_token, to, amount)?;
+                self._send(payment_token, self.ido.platform_treasury, fee)?;
+                self._emit_withdraw_raised_event(to, payment_token, amount, fee);
+                withdrawn_any = true;
+            }
+            ensure!(withdrawn_any, IDOError::NothingToWithdraw);
+            Ok(())
+        }
+
+        /// function to get total issued ido token, max issued ido token and total claimed ido token
+        #[ink(message)]
+        pub fn get_issued_amounts(&self) -> (Balance, Balance, Balance) {
+            (self.ido.issued_ido_amount, self.ido.max_issue_ido_amount, self.ido.total_claimed)
+        }
+
+        /// function to check the ido token accounting of the pool, returns the first violated invariant
+        #[ink(message)]
+        pub fn check_invariants(&self) -> Result<(), IDOError> {
+            ensure!(
+                self.ido.issued_ido_amount <= self.ido.max_issue_ido_amount,
+                IDOError::InvariantViolated(String::from("issued > max_issue_ido_amount"))
+            );
+            ensure!(
+                self.ido.total_claimed <= self.ido.issued_ido_amount,
+                IDOError::InvariantViolated(String::from("claimed > issued"))
+            );
+            let owed = self.ido.issued_ido_amount - self.ido.total_claimed;
+            ensure!(
+                PSP22Ref::balance_of(&self.ido.ido_token, self.env().account_id()) >= owed,
+                IDOError::InvariantViolated(String::from("pool balance < owed"))
+            );
+            Ok(())
+        }
+
+        /// common purchase flow once the buyer is authorised: price the payment, check the caps,
+        /// credit the ido token and pull PSP22 payments from the caller
+        fn _buy(&mut self, buyer: AccountId, phase_index: Option<u32>, payment_token: Option<AccountId>, amount: Balance, min_ido_amount_out: Balance) -> Result<Balance, IDOError> {
+            ensure!(self.ido.funded, IDOError::PoolNotFunded);
+            ensure!(self.ido.sale_mode != types::SaleMode::Overflow, IDOError::WrongSaleMode);
+            ensure!(amount > 0, IDOError::ZeroAmount);
+            let ido_amount = if self.ido.sale_mode == types::SaleMode::DutchAuction {
+                ensure!(payment_token.is_none(), IDOError::UnsupportedPaymentToken);
+                self._auction_buy(buyer, amount)?
+            } else {
+                let price = match payment_token {
+                    None => self._native_price(phase_index),
+                    Some(token) => self.get_token_price(token),
+                };
+                ensure!(price.is_valid(), IDOError::UnsupportedPaymentToken);
+                self._ido_for_payment(payment_token, amount, price)?
+            };
+            ensure!(ido_amount >= min_ido_amount_out, IDOError::SlippageExceeded);
+            self._add_phase_issued(phase_index, ido_amount)?;
+            self._add_contribution(buyer, payment_token, amount)?;
+            let new_balances = self._issue_ido(buyer, ido_amount)?;
+            self._spend_tier_allocation(buyer, phase_index, payment_token, amount)?;
+
+            if let Some(token) = payment_token {
+                self._pull_payment(token, amount)?;
+            }
+            Ok(new_balances)
+        }
+
+        /// function to get the ido token `buy_ido_with_native` gives for `payment_amount` at the current block
+        #[ink(message)]
+        pub fn quote_ido_amount(&self, payment_amount: Balance) -> Result<Balance, IDOError> {
+            let price = self._quote_price()?;
+            self._ido_for_payment(None, payment_amount, price)
+        }
+
+        /// function to get the native currency `buy_ido_with_native` needs for `ido_amount` at the current block, rounded up
+        #[ink(message)]
+        pub fn quote_payment_for(&self, ido_amount: Balance) -> Result<Balance, IDOError> {
+            let price = self._quote_price()?;
+            price
+                .payment_for_ido(ido_amount, types::NATIVE_DECIMALS, self.ido.token_decimals, true)
+                .ok_or(IDOError::ArithmeticOverflow)
+        }
+
+        /// native price of a purchase at the current block
+        fn _quote_price(&self) -> Result<types::Price, IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Active, IDOError::SaleClosed);
+            if self.ido.sale_mode == types::SaleMode::DutchAuction {
+                return Ok(self.current_price());
+            }
+            if self.ido.phases.is_empty() {
+                ensure!(!self._sale_end_passed(self.env().block_timestamp()), IDOError::SaleNotOpen);
+                return Ok(self._native_price(None));
+            }
+            let (index, _) = self.current_phase().ok_or(IDOError::SaleNotOpen)?;
+            Ok(self._native_price(Some(index)))
+        }
+
+        /// ido token bought with `amount` of the payment currency at `price`, `None` is the native currency
+        fn _ido_for_payment(&self, payment_token: Option<AccountId>, amount: Balance, price: types::Price) -> Result<Balance, IDOError> {
+            ensure!(price.is_valid(), IDOError::InvalidPrice);
+            price
+                .ido_for_payment(amount, self._payment_decimals(payment_token), self.ido.token_decimals)
+                .ok_or(IDOError::ArithmeticOverflow)
+        }
+
+        fn _payment_decimals(&self, payment_token: Option<AccountId>) -> u8 {
+            match payment_token {
+                None => types::NATIVE_DECIMALS,
+                Some(token) => self.ido.payment_token_decimals.get(&token).unwrap_or(0),
+            }
+        }
 
-    impl traits::Internal for IdoContract {
-        fn _verify(&self, data: String, signer: AccountId, signature: [u8; 65]) -> bool {
-            ink::env::debug_println!("data {:?}", data);
-            ink::env::debug_println!("signer {:?}", signer);
-            ink::env::debug_println!("signature {:?}", signature);
+        /// reads the decimals of a PSP22 token from its metadata
+        fn _token_decimals(&self, token: AccountId) -> Result<u8, IDOError> {
+            build_call::<Environment>()
+                .call(token)
+                .gas_limit(0)
+                .exec_input(ExecutionInput::new(Selector::new(ink::selector_bytes!("PSP22Metadata::token_decimals"))))
+                .returns::<u8>()
+                .try_invoke()
+                .map_err(|_| IDOError::TokenMetadataLookupFailed)?
+                .map_err(|_| IDOError::TokenMetadataLookupFailed)
+        }
 
-            let mut message_hash = <hash::Blake2x256 as hash::HashOutput>::Type::default();
-            ink::env::hash_bytes::<hash::Blake2x256>(&data.as_bytes(), &mut message_hash);
+        fn _init_ido(&mut self, ido_token: AccountId, signer: AccountId, price: types::Price, token_decimals: u8, max_issue_ido_amount: u128) -> Result<(), IDOError> {
+            ensure!(ido_token != ZERO_ADDRESS.into(), IDOError::ZeroTokenAddress);
+            ensure!(signer != ZERO_ADDRESS.into(), IDOError::ZeroSignerAddress);
+            ensure!(price.is_valid(), IDOError::InvalidPrice);
+            ensure!(token_decimals <= types::MAX_TOKEN_DECIMALS, IDOError::InvalidTokenDecimals);
+            ensure!(max_issue_ido_amount > 0, IDOError::ZeroMaxIssueAmount);
+            self.ido.ido_token = ido_token;
+            self.ido.price = price;
+            self.ido.token_decimals = token_decimals;
+            self.ido.signer = signer;
+            self.ido.max_issue_ido_amount = max_issue_ido_amount;
+            self.is_initialized = true;
 
-            ink::env::debug_println!("message_hash {:?}", message_hash);
+            self._emit_init_ido_contract_event(ido_token, price, token_decimals, signer, max_issue_ido_amount);
+            Ok(())
+        }
 
-            let output = self.env().ecdsa_recover(&signature, &message_hash).expect("Failed to recover");
+        fn _set_token_price(&mut self, payment_token: AccountId, price: types::Price, decimals: u8) {
+            self.ido.token_prices.insert(&payment_token, &price);
+            self.ido.payment_token_decimals.insert(&payment_token, &decimals);
+            if !self.ido.payment_tokens.contains(&payment_token) {
+                self.ido.payment_tokens.push(payment_token);
+            }
+            self._emit_set_token_price_event(payment_token, price);
+        }
 
-            ink::env::debug_println!("pubkey {:?}", output);
+        /// transfers approved PSP22 tokens from the caller to the pool
+        fn _pull_payment(&mut self, payment_token: AccountId, amount: Balance) -> Result<(), IDOError> {
+            let caller = self.env().caller();
+            if PSP22Ref::allowance(&payment_token, caller, self.env().account_id()) < amount {
+                return Err(IDOError::InsufficientAllowance)
+            }
 
-            let mut signature_account_id = <hash::Blake2x256 as hash::HashOutput>::Type::default();
-            ink::env::hash_encoded::<hash::Blake2x256, _>(&output, &mut signature_account_id);
+            let result = helpers::safe_transfer_from(payment_token, caller, self.env().account_id(), amount);
+            // check result
+            if result.is_err() {
+                return Err(IDOError::SafeTransferError);
+            }
+            Ok(())
+        }
 
-            ink::env::debug_println!("Sig account id {:?}", AccountId::from(signature_account_id));
+        /// function to choose between fixed price purchases and an oversubscribed pro-rata sale, only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn admin_set_sale_mode(&mut self, sale_mode: types::SaleMode) -> Result<(), IDOError> {
+            ensure!(self.ido.total_raised == 0, IDOError::SaleClosed);
+            if sale_mode == types::SaleMode::Overflow {
+                self._ensure_overflow_cap(self.ido.hard_cap, self.ido.price)?;
+            }
+            self.ido.sale_mode = sale_mode;
+            self._emit_set_sale_mode_event(sale_mode);
+            Ok(())
+        }
 
-            signer == AccountId::from(signature_account_id)
+        #[ink(message)]
+        pub fn get_sale_mode(&self) -> types::SaleMode {
+            self.ido.sale_mode
         }
 
-        fn _emit_buy_with_native_event(&self, _buyer: AccountId, _native_amount: Balance, _ido_token_amount: Balance, _nonce: u128) {
-            self.env().emit_event(BuyTokenWithNative {
-                buyer: _buyer,
-                native_amount: _native_amount,
-                new_ido_token_amount: _ido_token_amount,
-                nonce: _nonce,
-            });
+        /// function to commit native currency to an overflow sale
+        #[ink(message, payable)]
+        #[modifiers(when_not_paused)]
+        pub fn commit_native(&mut self) -> Result<(), IDOError> {
+            let received_value = Self::env().transferred_value();
+            self._commit(None, received_value)
         }
 
-        fn _emit_claim_token_event(&self, _buyer: AccountId, _ido_token_amount: Balance, _nonce: u128) {
-            self.env().emit_event(ClaimToken {
-                buyer: _buyer,
-                new_ido_token_amount: _ido_token_amount,
-                nonce: _nonce,
-            });
+        /// function to commit an accepted PSP22 payment token to an overflow sale, the buyer must approve `amount` for the pool first
+        #[ink(message)]
+        #[modifiers(when_not_paused)]
+        pub fn commit_with_token(&mut self, payment_token: AccountId, amount: Balance) -> Result<(), IDOError> {
+            ensure!(self.get_token_price(payment_token).is_valid(), IDOError::UnsupportedPaymentToken);
+            self._commit(Some(payment_token), amount)?;
+            self._pull_payment(payment_token, amount)
         }
 
-        fn _emit_init_ido_contract_event(&self, _ido_token: AccountId, _price: Balance, _price_decimals: u32, _signer: AccountId, _max_issue_ido_amount: u128) {
-            self.env().emit_event(InitIdoContract {
-                ido_token: _ido_token,
-                price: _price,
-                price_decimals: _price_decimals,
-                signer: _signer,
-                max_issue_ido_amount: _max_issue_ido_amount,
-            });
+        /// function to receive the pro-rata allocation of a finalized overflow sale and take back the unused commitment
+        #[ink(message)]
+        #[modifiers(when_not_paused)]
+        pub fn settle(&mut self) -> Result<(), IDOError> {
+            ensure!(self.ido.sale_mode == types::SaleMode::Overflow, IDOError::WrongSaleMode);
+            ensure!(self.ido.status == types::SaleStatus::Finalized, IDOError::SaleNotFinalized);
+            let caller = self.env().caller();
+            ensure!(!self.ido.settled.get(&caller).unwrap_or(false), IDOError::AlreadySettled);
+            let committed = self.committed_of(caller);
+            ensure!(committed > 0, IDOError::NothingToSettle);
+            self.ido.settled.insert(&caller, &true);
+            let contributed = self.contributed_value_of(caller);
+            self.ido.contributed_value.insert(&caller, &contributed.saturating_sub(self._overflow_excess(committed)));
+
+            let ido_amount = self.allocated_of(caller)?;
+            self._issue_ido(caller, ido_amount)?;
+
+            for payment_token in self._currencies() {
+                let contribution = self.contribution_of(caller, payment_token);
+                let excess = self._overflow_excess(contribution);
+                if excess == 0 {
+                    continue;
+                }
+                self.ido.user_contributions.insert(&(caller, payment_token), &(contribution - excess));
+                self._send(payment_token, caller, excess)?;
+                self._emit_refunded_event(caller, payment_token, excess, ido_amount);
+            }
+            self._emit_settled_event(caller, committed, ido_amount);
+            Ok(())
         }
-    }
 
+        /// function to get the value in native currency committed by the account to an overflow sale
+        #[ink(message)]
+        pub fn committed_of(&self, account: AccountId) -> Balance {
+            self.ido.committed.get(&account).unwrap_or(0)
+        }
 
-    impl traits::Ido for IdoContract {
-        /// this function is initialised function, will init the contract properties
+        /// function to get the ido token the account receives from an overflow sale, `commit * hard_cap / total_committed`
+        /// once the sale is oversubscribed, it is an estimate until the sale is finalized
         #[ink(message)]
-        fn init_ido(&mut self, _ido_token: AccountId, _signer: AccountId, _price: u128, _price_decimals: u32, _max_issue_ido_amount: u128) -> Result<(), IDOError> {
-            ensure!(self.is_initialized == false, IDOError::Initialized);
-            self.ido.ido_token = _ido_token;
-            self.ido.price = _price;
-            self.ido.price_decimals = _price_decimals;
-            self.ido.signer = _signer;
-            self.ido.max_issue_ido_amount = _max_issue_ido_amount;
-            self.is_initialized = true;
+        pub fn allocated_of(&self, account: AccountId) -> Result<Balance, IDOError> {
+            let committed = self.committed_of(account);
+            self._ido_for_payment(None, committed - self._overflow_excess(committed), self.ido.price)
+        }
+
+        /// function to get the value in native currency the account takes back when settling an overflow sale
+        #[ink(message)]
+        pub fn refundable_of(&self, account: AccountId) -> Balance {
+            if self.ido.settled.get(&account).unwrap_or(false) {
+                return 0;
+            }
+            self._overflow_excess(self.committed_of(account))
+        }
+
+        fn _commit(&mut self, payment_token: Option<AccountId>, amount: Balance) -> Result<(), IDOError> {
+            ensure!(self.ido.funded, IDOError::PoolNotFunded);
+            ensure!(self.ido.sale_mode == types::SaleMode::Overflow, IDOError::WrongSaleMode);
+            ensure!(amount > 0, IDOError::ZeroAmount);
+            self._open_phase()?;
 
-            self._emit_init_ido_contract_event(_ido_token, _price, _price_decimals, _signer, _max_issue_ido_amount);
+            let caller = self.env().caller();
+            let value = self._payment_value(payment_token, amount)?;
+            self._add_contribution(caller, payment_token, amount)?;
+            let committed = self.committed_of(caller) + value;
+            self.ido.committed.insert(&caller, &committed);
+
+            self._emit_committed_event(caller, payment_token, amount, committed);
             Ok(())
         }
 
-        /// get ido token
+        /// function to configure the Dutch auction used in `DutchAuction` sale mode, only admin can call this function
         #[ink(message)]
-        fn get_ido_token(&self) -> AccountId {
-            self.ido.ido_token
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn admin_set_dutch_auction(&mut self, auction: types::DutchAuction) -> Result<(), IDOError> {
+            ensure!(self.ido.total_raised == 0, IDOError::SaleClosed);
+            ensure!(auction.is_valid(), IDOError::InvalidAuctionConfig);
+            self.ido.auction = auction;
+            self._emit_set_dutch_auction_event(auction);
+            Ok(())
         }
 
         #[ink(message)]
-        fn get_nonce(&self, account: AccountId) -> u128 {
-            self.ido.account_nonce.get(&account).unwrap_or(0)
+        pub fn get_dutch_auction(&self) -> types::DutchAuction {
+            self.ido.auction
         }
 
-        /// function to buy ido token with native
-        #[ink(message, payable)]
-        fn buy_ido_with_native(&mut self, deadline: Timestamp, nonce: u128, signature: [u8; 65]) -> Result<(), IDOError> {
-            ensure!(
-                deadline >= self.env().block_timestamp(),
-                IDOError::Expired
-            );
-            ensure!(
-                nonce == self.ido.account_nonce.get(&self.env().caller()).unwrap_or(0),
-                IDOError::InvalidNonce(nonce.to_string())
-            );
+        /// function to get the auction price of one ido token in native currency
+        #[ink(message)]
+        pub fn current_price(&self) -> types::Price {
+            self.ido.auction.price_at(self.env().block_timestamp())
+        }
 
+        /// function to get the ido token bought with `payment_amount` native currency at `timestamp` in the auction
+        #[ink(message)]
+        pub fn quote_at(&self, timestamp: Timestamp, payment_amount: Balance) -> Result<Balance, IDOError> {
+            self._ido_for_payment(None, payment_amount, self.ido.auction.price_at(timestamp))
+        }
 
-            self.ido.account_nonce.insert(&self.env().caller(), &(nonce + 1));
+        /// function to get the lowest price paid in the auction so far, the final clearing price once the sale is finalized
+        #[ink(message)]
+        pub fn get_clearing_price(&self) -> types::Price {
+            self.ido.clearing_price
+        }
 
-            let received_value = Self::env().transferred_value();
+        /// function to get the native currency the account takes back when the auction settles at the clearing price
+        #[ink(message)]
+        pub fn rebate_of(&self, account: AccountId) -> Balance {
+            if !self.ido.auction.uniform_clearing || self.ido.rebate_claimed.get(&account).unwrap_or(false) {
+                return 0;
+            }
+            let paid = self.ido.auction_paid.get(&account).unwrap_or(0);
+            let bought = self.ido.auction_bought.get(&account).unwrap_or(0);
+            // the cost at the clearing price is rounded up so the rebates never exceed the reserve
+            match self.ido.clearing_price.payment_for_ido(bought, types::NATIVE_DECIMALS, self.ido.token_decimals, true) {
+                Some(cost) => paid.saturating_sub(cost),
+                None => 0,
+            }
+        }
 
-            ensure!(
-                self.ido.issued_ido_amount.checked_add(received_value).unwrap() <= self.ido.max_issue_ido_amount,
-                IDOError::MaxIssueIdoAmount,
-            );
+        /// function to take back the difference between the price paid and the clearing price of a finalized auction
+        #[ink(message)]
+        #[modifiers(when_not_paused)]
+        pub fn claim_rebate(&mut self) -> Result<(), IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Finalized, IDOError::SaleNotFinalized);
+            let caller = self.env().caller();
+            let rebate = self.rebate_of(caller);
+            ensure!(rebate > 0, IDOError::NothingToRebate);
+            self.ido.rebate_claimed.insert(&caller, &true);
 
-            // generate message = buy_ido + ido_token + buyer + amount
-            let message = self.gen_msg_for_buy_token(deadline, nonce, received_value);
+            let contribution = self.contribution_of(caller, None);
+            self.ido.user_contributions.insert(&(caller, None), &contribution.saturating_sub(rebate));
+            let contributed = self.contributed_value_of(caller);
+            self.ido.contributed_value.insert(&caller, &contributed.saturating_sub(rebate));
+            self._send(None, caller, rebate)?;
+            self._emit_auction_rebate_event(caller, self.ido.clearing_price, rebate);
+            Ok(())
+        }
 
-            // verify signature
-            let is_ok = self._verify(message, self.ido.signer, signature);
+        fn _auction_buy(&mut self, buyer: AccountId, amount: Balance) -> Result<Balance, IDOError> {
+            let now = self.env().block_timestamp();
+            ensure!(self.ido.auction.is_open(now), IDOError::SaleNotOpen);
+            let price = self.ido.auction.price_at(now);
+            let ido_amount = self._ido_for_payment(None, amount, price)?;
+            ensure!(ido_amount > 0, IDOError::ZeroAmount);
 
-            if !is_ok {
-                return Err(IDOError::InvalidSignature);
+            let paid = self.ido.auction_paid.get(&buyer).unwrap_or(0);
+            self.ido.auction_paid.insert(&buyer, &(paid + amount));
+            let bought = self.ido.auction_bought.get(&buyer).unwrap_or(0);
+            self.ido.auction_bought.insert(&buyer, &(bought + ido_amount));
+            self.ido.auction_paid_total += amount;
+            self.ido.auction_bought_total += ido_amount;
+            // the price only decays, so the latest purchase sets the clearing price
+            self.ido.clearing_price = price;
+            Ok(ido_amount)
+        }
+
+        /// native currency set aside for auction rebates not claimed yet
+        fn _rebate_reserve(&self) -> Balance {
+            if self.ido.sale_mode != types::SaleMode::DutchAuction || !self.ido.auction.uniform_clearing {
+                return 0;
             }
+            match self.ido.clearing_price.payment_for_ido(self.ido.auction_bought_total, types::NATIVE_DECIMALS, self.ido.token_decimals, false) {
+                Some(cost) => self.ido.auction_paid_total.saturating_sub(cost),
+                // keep everything paid when the cost cannot be computed
+                None => self.ido.auction_paid_total,
+            }
+        }
 
-            // calculate IDO amount = received_value * price / 10^price_decimals
-            let ido_amount = received_value.checked_mul(self.ido.price).unwrap().checked_div((10 as u128).checked_pow(self.ido.price_decimals).unwrap()).unwrap();
-            self.ido.issued_ido_amount = self.ido.issued_ido_amount.checked_add(ido_amount).unwrap();
+        /// part of `amount` above the hard cap of an oversubscribed overflow sale, rounded down
+        fn _overflow_excess(&self, amount: Balance) -> Balance {
+            if self.ido.sale_mode != types::SaleMode::Overflow || self.ido.hard_cap == 0 || self.ido.total_raised <= self.ido.hard_cap {
+                return 0;
+            }
+            helpers::proportion(amount, self.ido.total_raised - self.ido.hard_cap, self.ido.total_raised)
+        }
 
-            let old_balances = match self.ido.user_ido_balances.get(&self.env().caller()) {
-                Some(balance) => balance,
-                None => 0 as u128,
-            };
-            let new_balances = old_balances.checked_add(ido_amount).unwrap();
-            self.ido.user_ido_balances.insert(self.env().caller(), &new_balances);
+        /// an overflow sale issues its whole hard cap at `price`, so the cap must be set and fit in the issuable ido token
+        fn _ensure_overflow_cap(&self, hard_cap: Balance, price: types::Price) -> Result<(), IDOError> {
+            ensure!(hard_cap > 0, IDOError::InvalidCaps);
+            let ido_amount = self._ido_for_payment(None, hard_cap, price)?;
+            ensure!(ido_amount <= self.ido.max_issue_ido_amount, IDOError::InvalidCaps);
+            Ok(())
+        }
 
-            // emit event
-            self._emit_buy_with_native_event(self.env().caller(), received_value, new_balances, nonce);
+        /// function to select how purchases are authorised, only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn admin_set_allocation_mode(&mut self, mode: types::AllocationMode) -> Result<(), IDOError> {
+            self.ido.allocation_mode = mode;
+            self._emit_set_allocation_mode_event(mode);
             Ok(())
         }
 
-        /// function to claim ido token
         #[ink(message)]
-        fn claim_ido_token(&mut self, deadline: Timestamp, nonce: u128, amount: Balance, signature: [u8; 65]) -> Result<(), IDOError> {
-            ensure!(
-                deadline >= self.env().block_timestamp(),
-                IDOError::Expired
-            );
+        pub fn get_allocation_mode(&self) -> types::AllocationMode {
+            self.ido.allocation_mode
+        }
 
-            ensure!(
-                nonce == self.ido.account_nonce.get(&self.env().caller()).unwrap_or(0),
-                IDOError::InvalidNonce(nonce.to_string())
-            );
+        /// function to commit the Merkle root of `(account, max_allocation)` leaves, only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn admin_set_merkle_root(&mut self, merkle_root: [u8; 32]) -> Result<(), IDOError> {
+            self.ido.merkle_root = merkle_root;
+            self._emit_set_merkle_root_event(merkle_root);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn get_merkle_root(&self) -> [u8; 32] {
+            self.ido.merkle_root
+        }
 
-            self.ido.account_nonce.insert(&self.env().caller(), &(nonce + 1));
+        /// function to get the value in native currency the account already spent against its allowlist allocation
+        #[ink(message)]
+        pub fn allowlist_spent_of(&self, account: AccountId) -> Balance {
+            self.ido.allowlist_spent.get(&account).unwrap_or(0)
+        }
 
-            let caller = Self::env().caller();
-            // ensure the user has enough collateral assets
-            if PSP22Ref::balance_of(&self.ido.ido_token, self.env().account_id()) < amount {
-                return Err(IDOError::InsufficientBalance)
-            }
-            // generate message
-            let message = self.gen_msg_for_claim_token(deadline, nonce, amount);
+        /// function to revoke every allocation grant signed so far, only signer admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SIGNER_ADMIN))]
+        pub fn bump_allocation_epoch(&mut self) -> Result<(), IDOError> {
+            self.ido.allocation_epoch += 1;
+            self._emit_allocation_epoch_bumped_event(self.ido.allocation_epoch);
+            Ok(())
+        }
 
-            // verify signature
-            let is_ok = self._verify(message, self.ido.signer, signature);
+        #[ink(message)]
+        pub fn get_allocation_epoch(&self) -> u32 {
+            self.ido.allocation_epoch
+        }
 
-            if !is_ok {
-                return Err(IDOError::InvalidSignature);
-            }
+        /// function to get the value in native currency already spent against the allocation grant
+        #[ink(message)]
+        pub fn grant_spent_of(&self, grant: types::AllocationGrant) -> Balance {
+            self.ido.grant_spent.get(&self.gen_hash_for_allocation_grant(grant)).unwrap_or(0)
+        }
 
-            let old_balances = self.ido.user_ido_balances.get(&self.env().caller()).unwrap_or(0);
-            let new_balances = old_balances.checked_sub(amount).unwrap_or(0);
-            self.ido.user_ido_balances.insert(self.env().caller(), &new_balances);
+        /// function to get the hash the signer signs for an allocation grant
+        #[ink(message)]
+        pub fn gen_hash_for_allocation_grant(&self, grant: types::AllocationGrant) -> [u8; 32] {
+            helpers::hash_payload(&self._domain(types::SignedAction::AllocationGrant), &grant)
+        }
 
-            let result = helpers::safe_transfer(self.ido.ido_token, caller, amount);
-            // check result
-            if result.is_err() {
-                return Err(IDOError::SafeTransferError);
-            }
+        /// function to buy ido token with native for the grant buyer, spending part of a signed allocation grant
+        #[ink(message, payable)]
+        #[modifiers(when_not_paused)]
+        pub fn buy_ido_with_native_granted(&mut self, grant: types::AllocationGrant, signatures: Vec<[u8; 65]>, min_ido_amount_out: Balance) -> Result<(), IDOError> {
+            let received_value = Self::env().transferred_value();
+            let phase_index = self._open_phase()?;
+            let spent = self._spend_grant(grant, signatures, phase_index, None, received_value)?;
+            let new_balances = self._buy(grant.buyer, phase_index, None, received_value, min_ido_amount_out)?;
 
-            self._emit_claim_token_event(caller, new_balances, nonce);
+            self._emit_grant_purchase_event(self.env().caller(), grant.buyer, None, received_value, new_balances, spent);
             Ok(())
         }
 
-        /// function to set price of ido token, only admin can call this function
+        /// function to buy ido token with an accepted PSP22 payment token for the grant buyer, spending part of a signed allocation grant
         #[ink(message)]
-        #[modifiers(only_role(SUB_ADMIN))]
-        fn admin_set_price(&mut self, new_price: u128) -> Result<(), IDOError> {
-            self.ido.price = new_price;
+        #[modifiers(when_not_paused)]
+        pub fn buy_ido_with_token_granted(&mut self, payment_token: AccountId, amount: Balance, grant: types::AllocationGrant, signatures: Vec<[u8; 65]>, min_ido_amount_out: Balance) -> Result<(), IDOError> {
+            let phase_index = self._open_phase()?;
+            let spent = self._spend_grant(grant, signatures, phase_index, Some(payment_token), amount)?;
+            let new_balances = self._buy(grant.buyer, phase_index, Some(payment_token), amount, min_ido_amount_out)?;
+
+            self._emit_grant_purchase_event(self.env().caller(), grant.buyer, Some(payment_token), amount, new_balances, spent);
             Ok(())
         }
 
-        /// function to get price of ido token
+        fn _spend_grant(&mut self, grant: types::AllocationGrant, signatures: Vec<[u8; 65]>, phase_index: Option<u32>, payment_token: Option<AccountId>, amount: Balance) -> Result<Balance, IDOError> {
+            ensure!(self.ido.allocation_mode == types::AllocationMode::Signature, IDOError::WrongAllocationMode);
+            ensure!(grant.expiry >= self.env().block_timestamp(), IDOError::Expired);
+            ensure!(grant.epoch == self.ido.allocation_epoch, IDOError::GrantRevoked);
+            ensure!(grant.phase.is_none() || grant.phase == phase_index, IDOError::GrantPhaseMismatch);
+
+            let grant_hash = self.gen_hash_for_allocation_grant(grant);
+            ensure!(self._verify_signatures(grant_hash, &signatures), IDOError::InvalidSignature);
+
+            let spent = self.ido.grant_spent.get(&grant_hash).unwrap_or(0)
+                .checked_add(self._payment_value(payment_token, amount)?)
+                .ok_or(IDOError::AllocationExceeded)?;
+            ensure!(spent <= grant.max_allocation, IDOError::AllocationExceeded);
+            self.ido.grant_spent.insert(&grant_hash, &spent);
+            Ok(spent)
+        }
+
+        /// function to buy ido token with native in Merkle allowlist mode, `proof` shows `(caller, max_allocation)` is in the allowlist
+        #[ink(message, payable)]
+        #[modifiers(when_not_paused)]
+        pub fn buy_ido_with_native_allowlisted(&mut self, max_allocation: Balance, proof: Vec<[u8; 32]>, min_ido_amount_out: Balance) -> Result<(), IDOError> {
+            let caller = self.env().caller();
+            let received_value = Self::env().transferred_value();
+            let phase_index = self._open_phase()?;
+            let spent = self._spend_allowlist(caller, max_allocation, proof, None, received_value)?;
+            let new_balances = self._buy(caller, phase_index, None, received_value, min_ido_amount_out)?;
+
+            self._emit_allowlist_purchase_event(caller, None, received_value, new_balances, spent);
+            Ok(())
+        }
+
+        /// function to buy ido token with an accepted PSP22 payment token in Merkle allowlist mode
         #[ink(message)]
-        fn get_price(&self) -> u128 {
-            self.ido.price
+        #[modifiers(when_not_paused)]
+        pub fn buy_ido_with_token_allowlisted(&mut self, payment_token: AccountId, amount: Balance, max_allocation: Balance, proof: Vec<[u8; 32]>, min_ido_amount_out: Balance) -> Result<(), IDOError> {
+            let caller = self.env().caller();
+            let phase_index = self._open_phase()?;
+            let spent = self._spend_allowlist(caller, max_allocation, proof, Some(payment_token), amount)?;
+            let new_balances = self._buy(caller, phase_index, Some(payment_token), amount, min_ido_amount_out)?;
+
+            self._emit_allowlist_purchase_event(caller, Some(payment_token), amount, new_balances, spent);
+            Ok(())
         }
-    }
 
-    impl access_control::AccessControl for IdoContract {}
+        /// function to set the staking contract buyer tiers are read from and the allocation of each tier in native currency,
+        /// the allocations cap purchases during guaranteed phases, only admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SUB_ADMIN))]
+        pub fn admin_set_tier_allocations(&mut self, staking_contract: AccountId, tier_allocations: Vec<Balance>) -> Result<(), IDOError> {
+            self.ido.staking_contract = staking_contract;
+            self.ido.tier_allocations = tier_allocations.clone();
+            self._emit_set_tier_allocations_event(staking_contract, tier_allocations);
+            Ok(())
+        }
 
+        /// function to get the staking contract and the allocation of each tier
+        #[ink(message)]
+        pub fn get_tier_allocations(&self) -> (AccountId, Vec<Balance>) {
+            (self.ido.staking_contract, self.ido.tier_allocations.clone())
+        }
 
-    impl IdoContract {
-        /// constructor of IDO contract
-        #[ink(constructor)]
-        pub fn new(owner: AccountId) -> Self {
-            let mut instance = Self::default();
-            instance._init_with_admin(owner);
-            instance.is_initialized = false;
-            instance
+        /// function to get the guaranteed allocation of the account from its current staking tier
+        #[ink(message)]
+        pub fn tier_allocation_of(&self, account: AccountId) -> Result<Balance, IDOError> {
+            let tier = self._tier_of(account)?;
+            Ok(self.ido.tier_allocations.get(tier as usize).copied().unwrap_or(0))
         }
 
-        /// function to get balance of ido token
+        /// function to get the value in native currency the account already spent in guaranteed phases
         #[ink(message)]
-        pub fn get_ido_token_balance(&self, account: AccountId) -> u128 {
-            return self.ido.user_ido_balances.get(&account).unwrap_or(0 as u128);
+        pub fn guaranteed_spent_of(&self, account: AccountId) -> Balance {
+            self.ido.guaranteed_spent.get(&account).unwrap_or(0)
+        }
+
+        /// reads the tier of the account from the staking contract
+        fn _tier_of(&self, account: AccountId) -> Result<u128, IDOError> {
+            ensure!(self.ido.staking_contract != ZERO_ADDRESS.into(), IDOError::TierLookupFailed);
+            build_call::<Environment>()
+                .call(self.ido.staking_contract)
+                .gas_limit(0)
+                .exec_input(ExecutionInput::new(Selector::new(ink::selector_bytes!("tier_of"))).push_arg(account))
+                .returns::<u128>()
+                .try_invoke()
+                .map_err(|_| IDOError::TierLookupFailed)?
+                .map_err(|_| IDOError::TierLookupFailed)
+        }
+
+        /// caps purchases in a guaranteed phase at the tier allocation of the buyer
+        fn _spend_tier_allocation(&mut self, buyer: AccountId, phase_index: Option<u32>, payment_token: Option<AccountId>, amount: Balance) -> Result<(), IDOError> {
+            let guaranteed = match phase_index {
+                Some(index) => self.ido.phases[index as usize].kind == types::PhaseKind::Guaranteed,
+                None => false,
+            };
+            if !guaranteed || self.ido.staking_contract == ZERO_ADDRESS.into() {
+                return Ok(());
+            }
+            let allocation = self.tier_allocation_of(buyer)?;
+            let spent = self.guaranteed_spent_of(buyer)
+                .checked_add(self._payment_value(payment_token, amount)?)
+                .ok_or(IDOError::TierAllocationExceeded)?;
+            ensure!(spent <= allocation, IDOError::TierAllocationExceeded);
+            self.ido.guaranteed_spent.insert(&buyer, &spent);
+            Ok(())
+        }
+
+        /// checks the allowlist proof and adds the payment to the cumulative spend of the account
+        fn _spend_allowlist(&mut self, account: AccountId, max_allocation: Balance, proof: Vec<[u8; 32]>, payment_token: Option<AccountId>, amount: Balance) -> Result<Balance, IDOError> {
+            ensure!(self.ido.allocation_mode == types::AllocationMode::Merkle, IDOError::WrongAllocationMode);
+            let leaf = helpers::merkle_leaf(account, max_allocation);
+            ensure!(helpers::verify_merkle_proof(self.ido.merkle_root, leaf, &proof), IDOError::InvalidMerkleProof);
+
+            let spent = self.allowlist_spent_of(account)
+                .checked_add(self._payment_value(payment_token, amount)?)
+                .ok_or(IDOError::AllocationExceeded)?;
+            ensure!(spent <= max_allocation, IDOError::AllocationExceeded);
+            self.ido.allowlist_spent.insert(&account, &spent);
+            Ok(spent)
+        }
+
+        /// credits `ido_amount` to the buyer, the total issued amount never exceeds `max_issue_ido_amount`
+        fn _issue_ido(&mut self, buyer: AccountId, ido_amount: Balance) -> Result<Balance, IDOError> {
+            let issued = self.ido.issued_ido_amount.checked_add(ido_amount).ok_or(IDOError::MaxIssueIdoAmount)?;
+            ensure!(issued <= self.ido.max_issue_ido_amount, IDOError::MaxIssueIdoAmount);
+            self.ido.issued_ido_amount = issued;
+
+            let new_balances = self.get_ido_token_balance(buyer).checked_add(ido_amount).ok_or(IDOError::MaxIssueIdoAmount)?;
+            self.ido.user_ido_balances.insert(&buyer, &new_balances);
+            Ok(new_balances)
+        }
+
+        /// native currency followed by every configured PSP22 payment token
+        fn _currencies(&self) -> Vec<Option<AccountId>> {
+            let mut currencies: Vec<Option<AccountId>> = Vec::from([None]);
+            currencies.extend(self.ido.payment_tokens.iter().map(|token| Some(*token)));
+            currencies
+        }
+
+        fn _send(&self, payment_token: Option<AccountId>, to: AccountId, amount: Balance) -> Result<(), IDOError> {
+            if amount == 0 {
+                return Ok(());
+            }
+            match payment_token {
+                None => self.env().transfer(to, amount).map_err(|_| IDOError::NativeTransferError),
+                Some(token) => helpers::safe_transfer(token, to, amount).map_err(|_| IDOError::SafeTransferError),
+            }
+        }
+
+        /// value of a payment in native currency, PSP22 amounts are converted with the token price relative to the pool price
+        fn _payment_value(&self, payment_token: Option<AccountId>, amount: Balance) -> Result<Balance, IDOError> {
+            match payment_token {
+                None => Ok(amount),
+                Some(token) => {
+                    let ido_amount = self._ido_for_payment(payment_token, amount, self.get_token_price(token))?;
+                    self.ido.price
+                        .payment_for_ido(ido_amount, types::NATIVE_DECIMALS, self.ido.token_decimals, false)
+                        .ok_or(IDOError::ArithmeticOverflow)
+                }
+            }
+        }
+
+        fn _add_contribution(&mut self, buyer: AccountId, payment_token: Option<AccountId>, amount: Balance) -> Result<(), IDOError> {
+            let value = self._payment_value(payment_token, amount)?;
+            let total_raised = self.ido.total_raised
+                .checked_add(value)
+                .ok_or(IDOError::HardCapExceeded)?;
+            ensure!(
+                self.ido.sale_mode == types::SaleMode::Overflow || self.ido.hard_cap == 0 || total_raised <= self.ido.hard_cap,
+                IDOError::HardCapExceeded
+            );
+            self.ido.total_raised = total_raised;
+
+            let raised = self.ido.raised.get(&payment_token).unwrap_or(0) + amount;
+            self.ido.raised.insert(&payment_token, &raised);
+
+            let contribution = self.contribution_of(buyer, payment_token) + amount;
+            self.ido.user_contributions.insert(&(buyer, payment_token), &contribution);
+
+            let contributed = self.contributed_value_of(buyer) + value;
+            self.ido.contributed_value.insert(&buyer, &contributed);
+            self._register_participant(buyer);
+            Ok(())
+        }
+
+        fn _register_participant(&mut self, account: AccountId) {
+            if self.ido.participated.get(&account).unwrap_or(false) {
+                return;
+            }
+            self.ido.participated.insert(&account, &true);
+            self.ido.participant_at.insert(&self.ido.participant_count, &account);
+            self.ido.participant_count += 1;
+        }
+
+        fn contributed_value_of(&self, account: AccountId) -> Balance {
+            self.ido.contributed_value.get(&account).unwrap_or(0)
+        }
+
+        /// returns the open phase index, pools without phases are open until the sale end
+        fn _open_phase(&mut self) -> Result<Option<u32>, IDOError> {
+            ensure!(self.ido.status == types::SaleStatus::Active, IDOError::SaleClosed);
+            if self.ido.phases.is_empty() {
+                ensure!(!self._sale_end_passed(self.env().block_timestamp()), IDOError::SaleNotOpen);
+                return Ok(None);
+            }
+            let (index, phase) = self.current_phase().ok_or(IDOError::SaleNotOpen)?;
+            if self.ido.last_phase != Some(index) {
+                self.ido.last_phase = Some(index);
+                self._emit_phase_changed_event(index, phase);
+            }
+            Ok(Some(index))
+        }
+
+        fn _native_price(&self, phase_index: Option<u32>) -> types::Price {
+            phase_index
+                .and_then(|index| self.ido.phases[index as usize].price)
+                .unwrap_or(self.ido.price)
+        }
+
+        fn _add_phase_issued(&mut self, phase_index: Option<u32>, ido_amount: Balance) -> Result<(), IDOError> {
+            if let Some(index) = phase_index {
+                let issued = self.get_phase_issued(index).checked_add(ido_amount).ok_or(IDOError::PhaseCapExceeded)?;
+                ensure!(issued <= self.ido.phases[index as usize].cap, IDOError::PhaseCapExceeded);
+                self.ido.phase_issued.insert(&index, &issued);
+            }
+            Ok(())
         }
 
         // function to update code_hash (logic of IDO contract)
@@ -287,6 +2002,79 @@ pub mod ido {
             Ok(())
         }
 
+        /// function to set the chain identifier bound into signed payloads and whether the legacy string messages are verified instead
+        #[ink(message)]
+        #[modifiers(only_role(DEFAULT_ADMIN_ROLE))]
+        pub fn admin_set_signature_domain(&mut self, chain_id: u32, legacy_messages: bool) -> Result<(), IDOError> {
+            self.ido.chain_id = chain_id;
+            self.ido.legacy_messages = legacy_messages;
+            Ok(())
+        }
+
+        /// function to get the chain identifier and the legacy messages flag
+        #[ink(message)]
+        pub fn get_signature_domain(&self) -> (u32, bool) {
+            (self.ido.chain_id, self.ido.legacy_messages)
+        }
+
+        /// function to get the hash the signer signs for `buy_ido_with_native`
+        #[ink(message)]
+        pub fn gen_hash_for_buy_token(&self, payer: AccountId, beneficiary: AccountId, deadline: Timestamp, nonce: u128, received_value: Balance) -> [u8; 32] {
+            if self.ido.legacy_messages {
+                return helpers::hash_message(&self.gen_msg_for_buy_token(deadline, nonce, received_value));
+            }
+            helpers::hash_payload(&self._domain(types::SignedAction::BuyWithNative), &types::BuyPayload {
+                ido_token: self.ido.ido_token,
+                payer,
+                beneficiary,
+                amount: received_value,
+                deadline,
+                nonce,
+            })
+        }
+
+        /// function to get the hash the signer signs for `buy_ido_with_token`
+        #[ink(message)]
+        pub fn gen_hash_for_buy_token_with_token(&self, payer: AccountId, beneficiary: AccountId, payment_token: AccountId, amount: Balance, deadline: Timestamp, nonce: u128) -> [u8; 32] {
+            if self.ido.legacy_messages {
+                return helpers::hash_message(&self.gen_msg_for_buy_token_with_token(payment_token, amount, deadline, nonce));
+            }
+            helpers::hash_payload(&self._domain(types::SignedAction::BuyWithToken), &types::BuyWithTokenPayload {
+                ido_token: self.ido.ido_token,
+                payment_token,
+                payer,
+                beneficiary,
+                amount,
+                deadline,
+                nonce,
+            })
+        }
+
+        /// function to get the hash the signer signs for `claim_ido_token`
+        #[ink(message)]
+        pub fn gen_hash_for_claim_token(&self, buyer: AccountId, recipient: AccountId, deadline: Timestamp, nonce: u128, amount: Balance) -> [u8; 32] {
+            if self.ido.legacy_messages {
+                return helpers::hash_message(&self.gen_msg_for_claim_token(deadline, nonce, amount));
+            }
+            helpers::hash_payload(&self._domain(types::SignedAction::Claim), &types::ClaimPayload {
+                ido_token: self.ido.ido_token,
+                buyer,
+                recipient,
+                amount,
+                deadline,
+                nonce,
+            })
+        }
+
+        fn _domain(&self, action: types::SignedAction) -> types::Domain {
+            types::Domain {
+                contract: self.env().account_id(),
+                action,
+                chain_id: self.ido.chain_id,
+                version: types::SCHEMA_VERSION,
+            }
+        }
+
         #[ink(message)]
         pub fn gen_msg_for_buy_token(&self, deadline: Timestamp, nonce: u128, received_value: Balance) -> String {
             // generate message = buy_ido + ido_token + buyer + amount
@@ -305,6 +2093,32 @@ pub mod ido {
             message
         }
 
+        #[ink(message)]
+        pub fn gen_msg_for_buy_token_with_token(&self, payment_token: AccountId, amount: Balance, deadline: Timestamp, nonce: u128) -> String {
+            // generate message = buy_ido_with_token + ido_token + payment_token + buyer + amount
+            let mut message: String = String::from("");
+            message.push_str("buy_ido_with_token_");
+            message.push_str(encode(&self.ido.ido_token).as_str());
+            message.push_str("_");
+            message.push_str(encode(&payment_token).as_str());
+            message.push_str("_");
+            message.push_str(encode(&self.env().caller()).as_str());
+            message.push_str("_");
+            message.push_str(&amount.to_string().as_str());
+            message.push_str("_");
+            message.push_str(&deadline.to_string().as_str());
+            message.push_str("_");
+            message.push_str(&nonce.to_string().as_str());
+
+            message
+        }
+
+        /// function to get the list of PSP22 payment tokens that have ever been configured
+        #[ink(message)]
+        pub fn get_payment_tokens(&self) -> Vec<AccountId> {
+            self.ido.payment_tokens.clone()
+        }
+
         #[ink(message)]
         pub fn gen_msg_for_claim_token(&self, deadline: Timestamp, nonce: u128, amount: Balance) -> String {
             let mut message: String = String::from("");
@@ -322,19 +2136,174 @@ pub mod ido {
             message
         }
 
+        /// function to choose how signatures are verified, `eth_signer` is the H160 signer used in Ethereum mode
+        #[ink(message)]
+        #[modifiers(only_role(SIGNER_ADMIN))]
+        pub fn admin_set_signature_mode(&mut self, mode: types::SignatureMode, eth_signer: [u8; 20]) -> Result<(), IDOError> {
+            ensure!(mode != types::SignatureMode::Ethereum || eth_signer != [0; 20], IDOError::ZeroSignerAddress);
+            let old_eth_signer = self.ido.eth_signer;
+            self.ido.signature_mode = mode;
+            self.ido.eth_signer = eth_signer;
+            // only the signers of the new mode can approve actions from now on
+            ensure!(self._permanent_signers().len() as u32 >= self.ido.signer_threshold, IDOError::InvalidThreshold);
+            if old_eth_signer != eth_signer {
+                self._emit_eth_signer_changed_event(old_eth_signer, eth_signer);
+            }
+            self._emit_set_signature_mode_event(mode, eth_signer);
+            Ok(())
+        }
+
+        /// function to get the signature mode and the H160 signer
+        #[ink(message)]
+        pub fn get_signature_mode(&self) -> (types::SignatureMode, [u8; 20]) {
+            (self.ido.signature_mode, self.ido.eth_signer)
+        }
+
         #[ink(message)]
         pub fn get_signer(&self) -> AccountId {
             self.ido.signer
         }
 
+        /// function to replace the signer immediately, only signer admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SIGNER_ADMIN))]
+        pub fn set_signer(&mut self, _new_signer: AccountId) -> Result<(), IDOError> {
+            ensure!(_new_signer != ZERO_ADDRESS.into(), IDOError::ZeroSignerAddress);
+            let old_signer = self.ido.signer;
+            self.ido.signer = _new_signer;
+            self.ido.pending_signer = None;
+            self.ido.previous_signer = None;
+            self._emit_signer_changed_event(old_signer, _new_signer, 0);
+            Ok(())
+        }
+
+        /// function to set the delay before a proposed signer can be activated and how long the old signer stays valid after
+        #[ink(message)]
+        #[modifiers(only_role(SIGNER_ADMIN))]
+        pub fn set_signer_rotation(&mut self, delay: Timestamp, grace_period: Timestamp) -> Result<(), IDOError> {
+            self.ido.signer_rotation_delay = delay;
+            self.ido.signer_grace_period = grace_period;
+            Ok(())
+        }
+
+        /// function to get the signer rotation delay and grace period
+        #[ink(message)]
+        pub fn get_signer_rotation(&self) -> (Timestamp, Timestamp) {
+            (self.ido.signer_rotation_delay, self.ido.signer_grace_period)
+        }
+
+        /// function to propose a new signer, it can be activated once the rotation delay passed
+        #[ink(message)]
+        #[modifiers(only_role(SIGNER_ADMIN))]
+        pub fn propose_signer(&mut self, new_signer: AccountId) -> Result<(), IDOError> {
+            ensure!(new_signer != ZERO_ADDRESS.into(), IDOError::ZeroSignerAddress);
+            let activate_at = self.env().block_timestamp() + self.ido.signer_rotation_delay;
+            self.ido.pending_signer = Some((new_signer, activate_at));
+            self._emit_signer_proposed_event(new_signer, activate_at);
+            Ok(())
+        }
+
+        /// function to activate the proposed signer, the old signer stays valid during the grace period
+        #[ink(message)]
+        #[modifiers(only_role(SIGNER_ADMIN))]
+        pub fn activate_signer(&mut self) -> Result<(), IDOError> {
+            let (new_signer, activate_at) = self.ido.pending_signer.ok_or(IDOError::NoPendingSigner)?;
+            let now = self.env().block_timestamp();
+            ensure!(now >= activate_at, IDOError::SignerRotationDelay);
+
+            let old_signer = self.ido.signer;
+            let grace_until = now + self.ido.signer_grace_period;
+            self.ido.signer = new_signer;
+            self.ido.pending_signer = None;
+            self.ido.previous_signer = Some((old_signer, grace_until));
+            self._emit_signer_changed_event(old_signer, new_signer, grace_until);
+            Ok(())
+        }
+
+        /// function to get the proposed signer and the time it can be activated
+        #[ink(message)]
+        pub fn get_pending_signer(&self) -> Option<(AccountId, Timestamp)> {
+            self.ido.pending_signer
+        }
+
+        /// function to add a signer to the signer set, only signer admin can call this function
+        #[ink(message)]
+        #[modifiers(only_role(SIGNER_ADMIN))]
+        pub fn add_signer(&mut self, signer: types::SignerId) -> Result<(), IDOError> {
+            ensure!(!self.ido.signers.contains(&signer), IDOError::SignerExists);
+            self.ido.signers.push(signer);
+            self._emit_signer_added_event(signer);
+            Ok(())
+        }
+
+        /// function to remove a signer from the signer set, the remaining signers must still reach the threshold
+        #[ink(message)]
+        #[modifiers(only_role(SIGNER_ADMIN))]
+        pub fn remove_signer(&mut self, signer: types::SignerId) -> Result<(), IDOError> {
+            let index = self.ido.signers.iter().position(|s| *s == signer).ok_or(IDOError::SignerNotFound)?;
+            self.ido.signers.remove(index);
+            ensure!(self._permanent_signers().len() as u32 >= self.ido.signer_threshold, IDOError::InvalidThreshold);
+            self._emit_signer_removed_event(signer);
+            Ok(())
+        }
+
+        /// function to set how many distinct signers must sign an action
         #[ink(message)]
-        pub fn set_signer(&mut self, _new_signer: AccountId) {
-            self.ido.signer = _new_signer
+        #[modifiers(only_role(SIGNER_ADMIN))]
+        pub fn set_signer_threshold(&mut self, threshold: u32) -> Result<(), IDOError> {
+            // the previous signer only counts until its grace period ends, so it cannot help reach the threshold
+            ensure!(threshold > 0 && threshold <= self._permanent_signers().len() as u32, IDOError::InvalidThreshold);
+            self.ido.signer_threshold = threshold;
+            self._emit_signer_threshold_changed_event(threshold);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn get_signer_threshold(&self) -> u32 {
+            self.ido.signer_threshold
+        }
+
+        /// function to get the signers accepted in the current signature mode
+        #[ink(message)]
+        pub fn get_signers(&self) -> Vec<types::SignerId> {
+            let mut signers = self._permanent_signers();
+            if let (Some((previous, grace_until)), types::SignatureMode::Substrate) = (self.ido.previous_signer, self.ido.signature_mode) {
+                let previous = types::SignerId::Substrate(previous);
+                if self.env().block_timestamp() < grace_until && !signers.contains(&previous) {
+                    signers.insert(1, previous);
+                }
+            }
+            signers
         }
 
+        /// signers of the current signature mode without the previous signer kept during a rotation grace period
+        fn _permanent_signers(&self) -> Vec<types::SignerId> {
+            let mut signers: Vec<types::SignerId> = Vec::new();
+            let primary = match self.ido.signature_mode {
+                types::SignatureMode::Substrate => types::SignerId::Substrate(self.ido.signer),
+                types::SignatureMode::Ethereum => types::SignerId::Ethereum(self.ido.eth_signer),
+            };
+            signers.push(primary);
+            for signer in self.ido.signers.iter() {
+                let same_mode = match (signer, self.ido.signature_mode) {
+                    (types::SignerId::Substrate(_), types::SignatureMode::Substrate) => true,
+                    (types::SignerId::Ethereum(_), types::SignatureMode::Ethereum) => true,
+                    _ => false,
+                };
+                if same_mode && !signers.contains(signer) {
+                    signers.push(*signer);
+                }
+            }
+            signers
+        }
+
+        /// function to check the signature of `msg` comes from one of the signers
         #[ink(message)]
         pub fn verify_signature(&self, signature: [u8; 65], msg: String) -> bool {
-            self._verify(msg, self.ido.signer, signature)
+            match self._recover_signer(helpers::hash_message(&msg), signature) {
+                Some(signer) => self._is_signer(&signer),
+                None => false,
+            }
         }
     }
 
@@ -348,36 +2317,583 @@ pub mod ido {
         use openbrush::traits::AccountIdExt;
         use super::*;
 
+        /// pool owned by bob, who is the caller and sub admin, selling up to 100_000 ido token at one native unit each
+        fn sub_admin_pool() -> IdoContract {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.bob);
+            assert_eq!(ido._init_ido(accounts.django, accounts.alice, types::Price::new(1, 1), 12, 100_000), Ok(()));
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert_eq!(ido.grant_role(SUB_ADMIN, accounts.bob), Ok(()));
+            ido
+        }
+
+        /// funded overflow pool with a hard cap of 1000 native units
+        fn overflow_pool() -> IdoContract {
+            let mut ido = sub_admin_pool();
+            ido.ido.funded = true;
+            assert_eq!(ido.admin_set_caps(0, 1000), Ok(()));
+            assert_eq!(ido.admin_set_sale_mode(types::SaleMode::Overflow), Ok(()));
+            ido
+        }
+
         #[ink::test]
         fn initialize_works() {
             let accounts = default_accounts::<ink::env::DefaultEnvironment>();
             let mut ido = IdoContract::new(accounts.alice);
-            &ido.init_ido(accounts.bob, accounts.alice, 10, 1);
+            assert_eq!(ido._init_ido(accounts.bob, accounts.alice, types::Price::new(1, 10), 18, 1000), Ok(()));
             assert_eq!(ido.ido.ido_token, accounts.bob);
-            assert_eq!(ido.ido.price, 10);
-            assert_eq!(ido.ido.price_decimals, 1);
+            assert_eq!(ido.ido.price, types::Price::new(1, 10));
+            assert_eq!(ido.ido.token_decimals, 18);
+            assert_eq!(ido.init_ido(accounts.bob, accounts.alice, types::Price::new(1, 10), 1000), Err(IDOError::Initialized));
             assert_eq!(ido.ido.signer, accounts.alice);
         }
 
+        #[ink::test]
+        fn init_ido_is_restricted_and_validated() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.alice);
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert_eq!(
+                ido.init_ido(accounts.django, accounts.alice, types::Price::new(1, 10), 1000),
+                Err(IDOError::Custom(String::from("AC::MissingRole")))
+            );
+
+            let zero = AccountId::from([0u8; 32]);
+            assert_eq!(ido._init_ido(zero, accounts.alice, types::Price::new(1, 10), 18, 1000), Err(IDOError::ZeroTokenAddress));
+            assert_eq!(ido._init_ido(accounts.django, zero, types::Price::new(1, 10), 18, 1000), Err(IDOError::ZeroSignerAddress));
+            assert_eq!(ido._init_ido(accounts.django, accounts.alice, types::Price::new(0, 10), 18, 1000), Err(IDOError::InvalidPrice));
+            assert_eq!(ido._init_ido(accounts.django, accounts.alice, types::Price::new(1, 10), 40, 1000), Err(IDOError::InvalidTokenDecimals));
+            assert_eq!(ido._init_ido(accounts.django, accounts.alice, types::Price::new(1, 10), 18, 0), Err(IDOError::ZeroMaxIssueAmount));
+        }
+
         #[ink::test]
         fn set_signer_works() {
             let accounts = default_accounts::<ink::env::DefaultEnvironment>();
             let mut ido = IdoContract::new(accounts.alice);
-            &ido.init_ido(accounts.bob, accounts.alice, 10, 1);
-            &ido.set_signer(accounts.bob);
+            assert_eq!(ido._init_ido(accounts.bob, accounts.alice, types::Price::new(1, 1), 12, 1000), Ok(()));
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert!(ido.set_signer(accounts.bob).is_err());
+            ink::env::test::set_caller::<Environment>(accounts.alice);
+            assert_eq!(ido.grant_role(SIGNER_ADMIN, accounts.alice), Ok(()));
+            assert_eq!(ido.set_signer(accounts.bob), Ok(()));
+            assert_eq!(ido.ido.signer, accounts.bob);
+
+            let zero = AccountId::from([0u8; 32]);
+            assert_eq!(ido.set_signer(zero), Err(IDOError::ZeroSignerAddress));
+            assert_eq!(ido.propose_signer(zero), Err(IDOError::ZeroSignerAddress));
+            assert_eq!(ido.admin_set_signature_mode(types::SignatureMode::Ethereum, [0; 20]), Err(IDOError::ZeroSignerAddress));
             assert_eq!(ido.ido.signer, accounts.bob);
         }
 
+        #[ink::test]
+        fn ethereum_signatures_are_recovered() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.alice);
+            assert_eq!(ido._init_ido(accounts.django, accounts.alice, types::Price::new(1, 1), 12, 1000), Ok(()));
+            assert_eq!(ido.grant_role(SIGNER_ADMIN, accounts.alice), Ok(()));
+            // personal-sign of [1; 32] with the private key 1
+            let eth_signer: [u8; 20] = hex::decode("7e5f4552091a69125d5dfcb7b8c2659029395bdf").unwrap().try_into().unwrap();
+            let signature: [u8; 65] = hex::decode(
+                "efa29f89d9851433e6f357243636876fa79968c86e8bc750cd45371a347d469d063dd56b7cf04160b95bf23aa4ddbec9a8891a63f224a4ab09645ef5fe2ff64c1b"
+            ).unwrap().try_into().unwrap();
+            let eth_hash: [u8; 32] = hex::decode("e25a5f1c47bcaecf506355e8536bc56a1cf4acb60b28defe1a24dcde73c389d1").unwrap().try_into().unwrap();
+            assert_eq!(helpers::eth_message_hash(&[1; 32]), eth_hash);
+
+            assert_eq!(ido.admin_set_signature_mode(types::SignatureMode::Ethereum, eth_signer), Ok(()));
+            assert_eq!(ido._recover_signer([1; 32], signature), Some(types::SignerId::Ethereum(eth_signer)));
+            assert!(ido._verify_signatures([1; 32], &[signature]));
+            assert!(!ido._verify_signatures([2; 32], &[signature]));
+
+            // a second Substrate signer does not count once Ethereum signatures are required
+            assert_eq!(ido.admin_set_signature_mode(types::SignatureMode::Substrate, eth_signer), Ok(()));
+            assert_eq!(ido.add_signer(types::SignerId::Substrate(accounts.bob)), Ok(()));
+            assert_eq!(ido.set_signer_threshold(2), Ok(()));
+            assert_eq!(
+                ido.admin_set_signature_mode(types::SignatureMode::Ethereum, eth_signer),
+                Err(IDOError::InvalidThreshold)
+            );
+        }
+
+        #[ink::test]
+        fn two_step_signer_rotation_works() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.alice);
+            assert_eq!(ido._init_ido(accounts.bob, accounts.alice, types::Price::new(1, 1), 12, 1000), Ok(()));
+            assert_eq!(ido.grant_role(SIGNER_ADMIN, accounts.alice), Ok(()));
+            assert_eq!(ido.set_signer_rotation(100, 50), Ok(()));
+            assert_eq!(ido.propose_signer(accounts.charlie), Ok(()));
+            assert_eq!(ido.activate_signer(), Err(IDOError::SignerRotationDelay));
+
+            ink::env::test::set_block_timestamp::<Environment>(100);
+            assert_eq!(ido.activate_signer(), Ok(()));
+            assert_eq!(ido.get_signer(), accounts.charlie);
+            assert_eq!(ido.get_signers(), vec![types::SignerId::Substrate(accounts.charlie), types::SignerId::Substrate(accounts.alice)]);
+            // alice is only accepted until the grace period ends, so she does not count towards the threshold
+            assert_eq!(ido.set_signer_threshold(2), Err(IDOError::InvalidThreshold));
+
+            ink::env::test::set_block_timestamp::<Environment>(150);
+            assert_eq!(ido.get_signers(), vec![types::SignerId::Substrate(accounts.charlie)]);
+            assert_eq!(ido.activate_signer(), Err(IDOError::NoPendingSigner));
+        }
+
         #[ink::test]
         fn admin_set_price_works() {
             let accounts = default_accounts::<ink::env::DefaultEnvironment>();
             let mut ido = IdoContract::new(accounts.bob);
-            &ido.init_ido(accounts.bob, accounts.alice, 10, 1);
-            &ido.admin_set_price(20);
-            assert_eq!(ido.ido.price, 10);
+            assert_eq!(ido._init_ido(accounts.bob, accounts.alice, types::Price::new(1, 10), 12, 1000), Ok(()));
+            assert!(ido.admin_set_price(types::Price::new(1, 20)).is_err());
+            assert_eq!(ido.ido.price, types::Price::new(1, 10));
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert_eq!(ido.grant_role(SUB_ADMIN, accounts.bob), Ok(()));
+            assert_eq!(ido.admin_set_price(types::Price::new(1, 0)), Err(IDOError::InvalidPrice));
+            assert_eq!(ido.admin_set_price(types::Price::new(1, 20)), Ok(()));
+            assert_eq!(ido.ido.price, types::Price::new(1, 20));
+            ido.ido.status = types::SaleStatus::Finalized;
+            assert_eq!(ido.admin_set_price(types::Price::new(1, 40)), Err(IDOError::SaleClosed));
+            assert_eq!(ido.ido.price, types::Price::new(1, 20));
+        }
+
+        #[ink::test]
+        fn admin_set_token_price_works() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.bob);
+            assert_eq!(ido._init_ido(accounts.bob, accounts.alice, types::Price::new(1, 1), 12, 1000), Ok(()));
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert_eq!(ido.grant_role(SUB_ADMIN, accounts.bob), Ok(()));
+            assert_eq!(ido.admin_set_token_price(accounts.bob, types::Price::new(1, 20)), Err(IDOError::UnsupportedPaymentToken));
+            ido._set_token_price(accounts.charlie, types::Price::new(1, 20), 6);
+            assert_eq!(ido.get_token_price(accounts.charlie), types::Price::new(1, 20));
+            assert_eq!(ido.get_payment_tokens(), vec![accounts.charlie]);
+        }
+
+        #[ink::test]
+        fn vested_amount_works() {
+            let vesting = types::VestingConfig {
+                tge_percent: 1000,
+                tge_timestamp: 100,
+                cliff_timestamp: 200,
+                linear_duration: 3 * types::MONTH,
+                monthly_step: false,
+            };
+            assert_eq!(vesting.vested_amount(1000, 99), 0);
+            assert_eq!(vesting.vested_amount(1000, 100), 100);
+            assert_eq!(vesting.vested_amount(1000, 200 + types::MONTH), 400);
+            assert_eq!(vesting.vested_amount(1000, 200 + 3 * types::MONTH), 1000);
+
+            let stepped = types::VestingConfig { monthly_step: true, ..vesting };
+            assert_eq!(stepped.vested_amount(1000, 200 + types::MONTH - 1), 100);
+            assert_eq!(stepped.vested_amount(1000, 200 + 2 * types::MONTH), 700);
+        }
+
+        #[ink::test]
+        fn vesting_is_locked_after_purchases() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = sub_admin_pool();
+            let vesting = types::VestingConfig {
+                tge_percent: 1000,
+                tge_timestamp: 100,
+                cliff_timestamp: 200,
+                linear_duration: types::MONTH,
+                monthly_step: false,
+            };
+            assert_eq!(ido.admin_set_vesting(vesting), Ok(()));
+
+            assert_eq!(ido._add_contribution(accounts.charlie, None, 1000), Ok(()));
+            assert!(ido._issue_ido(accounts.charlie, 1000).is_ok());
+            let delayed = types::VestingConfig { cliff_timestamp: 100 * types::MONTH, ..vesting };
+            assert_eq!(ido.admin_set_vesting(delayed), Err(IDOError::VestingLocked));
+            assert_eq!(ido.get_vesting(), vesting);
+
+            ido.ido.status = types::SaleStatus::Finalized;
+            assert_eq!(ido.admin_set_vesting(delayed), Err(IDOError::SaleClosed));
+        }
+
+        #[ink::test]
+        fn current_phase_works() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.bob);
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert_eq!(ido.grant_role(SUB_ADMIN, accounts.bob), Ok(()));
+            let guaranteed = types::Phase { kind: types::PhaseKind::Guaranteed, start: 0, end: 100, price: Some(types::Price::new(1, 1)), cap: 1000 };
+            let public = types::Phase { kind: types::PhaseKind::Public, start: 100, end: 200, price: None, cap: 1000 };
+            assert_eq!(ido.admin_set_phases(vec![public, guaranteed]), Err(IDOError::InvalidPhaseConfig));
+            assert_eq!(ido.admin_set_phases(vec![guaranteed, public]), Ok(()));
+            assert_eq!(ido.current_phase(), Some((0, guaranteed)));
+            ink::env::test::set_block_timestamp::<Environment>(150);
+            assert_eq!(ido.current_phase(), Some((1, public)));
+            ink::env::test::set_block_timestamp::<Environment>(200);
+            assert_eq!(ido.current_phase(), None);
+
+            ido.ido.issued_ido_amount = 10;
+            assert_eq!(ido.admin_set_phases(vec![public]), Err(IDOError::PhasesLocked));
+            assert_eq!(ido.get_phases(), vec![guaranteed, public]);
+        }
+
+        #[ink::test]
+        fn finalize_switches_to_refunding_below_soft_cap() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.bob);
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert_eq!(ido.grant_role(SUB_ADMIN, accounts.bob), Ok(()));
+            let public = types::Phase { kind: types::PhaseKind::Public, start: 0, end: 100, price: None, cap: 1000 };
+            assert_eq!(ido.admin_set_phases(vec![public]), Ok(()));
+            assert_eq!(ido.admin_set_caps(200, 100), Err(IDOError::InvalidCaps));
+            assert_eq!(ido.admin_set_caps(100, 200), Ok(()));
+            assert_eq!(ido.finalize(), Err(IDOError::SaleNotEnded));
+            ink::env::test::set_block_timestamp::<Environment>(100);
+            assert_eq!(ido.finalize(), Ok(()));
+            assert_eq!(ido.get_sale_status(), types::SaleStatus::Refunding);
+            assert_eq!(ido.refund(), Err(IDOError::NothingToRefund));
+        }
+
+        #[ink::test]
+        fn sale_without_phases_finalizes_after_sale_end() {
+            let mut ido = sub_admin_pool();
+            assert_eq!(ido.finalize(), Err(IDOError::SaleNotEnded));
+
+            assert_eq!(ido.admin_set_sale_end(100), Ok(()));
+            assert_eq!(ido._quote_price(), Ok(types::Price::new(1, 1)));
+            ink::env::test::set_block_timestamp::<Environment>(100);
+            assert_eq!(ido._quote_price(), Err(IDOError::SaleNotOpen));
+            assert_eq!(ido.finalize(), Ok(()));
+            assert_eq!(ido.get_sale_status(), types::SaleStatus::Finalized);
+            assert_eq!(ido.admin_set_sale_end(200), Err(IDOError::SaleClosed));
+        }
+
+        #[ink::test]
+        fn withdraw_raised_requires_finalized_sale() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.bob);
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert_eq!(ido.grant_role(SUB_ADMIN, accounts.bob), Ok(()));
+            assert_eq!(ido.admin_set_platform_fee(10_001, accounts.charlie), Err(IDOError::InvalidPlatformFee));
+            assert_eq!(ido.admin_set_platform_fee(250, ZERO_ADDRESS.into()), Err(IDOError::InvalidPlatformFee));
+            assert_eq!(ido.admin_set_platform_fee(250, accounts.charlie), Ok(()));
+            assert_eq!(ido.get_platform_fee(), (250, accounts.charlie));
+            assert_eq!(ido.withdraw_raised(accounts.django), Err(IDOError::SaleNotFinalized));
+        }
+
+        #[ink::test]
+        fn withdraw_raised_splits_the_platform_fee() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = sub_admin_pool();
+            assert_eq!(ido.admin_set_platform_fee(250, accounts.charlie), Ok(()));
+            ink::env::test::set_account_balance::<Environment>(ink::env::account_id::<Environment>(), 10_000);
+            assert_eq!(ido._add_contribution(accounts.eve, None, 10_000), Ok(()));
+            assert_eq!(ido.admin_set_sale_end(100), Ok(()));
+            ink::env::test::set_block_timestamp::<Environment>(100);
+            assert_eq!(ido.finalize(), Ok(()));
+
+            let to_balance = ink::env::test::get_account_balance::<Environment>(accounts.frank).unwrap_or(0);
+            let treasury_balance = ink::env::test::get_account_balance::<Environment>(accounts.charlie).unwrap_or(0);
+            assert_eq!(ido.withdraw_raised(accounts.frank), Ok(()));
+            assert_eq!(ink::env::test::get_account_balance::<Environment>(accounts.frank), Ok(to_balance + 9_750));
+            assert_eq!(ink::env::test::get_account_balance::<Environment>(accounts.charlie), Ok(treasury_balance + 250));
+            assert_eq!(ido.raised_funds(None), (10_000, 9_750, 250));
+            assert_eq!(ido.withdraw_raised(accounts.frank), Err(IDOError::NothingToWithdraw));
+        }
+
+        #[ink::test]
+        fn issue_ido_respects_max_issue_amount() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.bob);
+            assert_eq!(ido._init_ido(accounts.bob, accounts.alice, types::Price::new(1, 1), 12, 1000), Ok(()));
+            assert_eq!(ido._issue_ido(accounts.charlie, 600), Ok(600));
+            assert_eq!(ido._issue_ido(accounts.django, 401), Err(IDOError::MaxIssueIdoAmount));
+            assert_eq!(ido._issue_ido(accounts.charlie, 400), Ok(1000));
+            assert_eq!(ido.get_issued_amounts(), (1000, 1000, 0));
+        }
+
+        #[ink::test]
+        fn signed_payload_hash_is_domain_separated() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.alice);
+            assert_eq!(ido._init_ido(accounts.bob, accounts.alice, types::Price::new(1, 1), 12, 1000), Ok(()));
+            let buy_hash = ido.gen_hash_for_buy_token(accounts.alice, accounts.alice, 100, 0, 10);
+            assert_ne!(buy_hash, ido.gen_hash_for_claim_token(accounts.alice, accounts.alice, 100, 0, 10));
+            // payer and beneficiary are both bound
+            assert_ne!(buy_hash, ido.gen_hash_for_buy_token(accounts.alice, accounts.charlie, 100, 0, 10));
+            assert_ne!(buy_hash, ido.gen_hash_for_buy_token(accounts.charlie, accounts.alice, 100, 0, 10));
+
+            assert_eq!(ido.admin_set_signature_domain(1, false), Ok(()));
+            assert_ne!(buy_hash, ido.gen_hash_for_buy_token(accounts.alice, accounts.alice, 100, 0, 10));
+
+            assert_eq!(ido.admin_set_signature_domain(1, true), Ok(()));
+            let legacy_message = ido.gen_msg_for_buy_token(100, 0, 10);
+            assert_eq!(ido.gen_hash_for_buy_token(accounts.alice, accounts.alice, 100, 0, 10), helpers::hash_message(&legacy_message));
+        }
+
+        #[ink::test]
+        fn signer_set_works() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.alice);
+            assert_eq!(ido._init_ido(accounts.bob, accounts.alice, types::Price::new(1, 1), 12, 1000), Ok(()));
+            assert_eq!(ido.grant_role(SIGNER_ADMIN, accounts.alice), Ok(()));
+            let bob = types::SignerId::Substrate(accounts.bob);
+            assert_eq!(ido.set_signer_threshold(2), Err(IDOError::InvalidThreshold));
+            assert_eq!(ido.add_signer(bob), Ok(()));
+            assert_eq!(ido.add_signer(types::SignerId::Ethereum([1; 20])), Ok(()));
+            assert_eq!(ido.add_signer(bob), Err(IDOError::SignerExists));
+            assert_eq!(ido.get_signers(), vec![types::SignerId::Substrate(accounts.alice), bob]);
+            assert_eq!(ido.set_signer_threshold(2), Ok(()));
+            assert_eq!(ido.remove_signer(bob), Err(IDOError::InvalidThreshold));
+            assert_eq!(ido.verify_signature([0; 65], String::from("message")), false);
+        }
+
+        #[ink::test]
+        fn merkle_proof_works() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let alice = helpers::merkle_leaf(accounts.alice, 100);
+            let bob = helpers::merkle_leaf(accounts.bob, 200);
+            let charlie = helpers::merkle_leaf(accounts.charlie, 300);
+            let alice_bob = helpers::hash_pair(alice, bob);
+            let root = helpers::hash_pair(alice_bob, charlie);
+
+            assert!(helpers::verify_merkle_proof(root, alice, &[bob, charlie]));
+            assert!(helpers::verify_merkle_proof(root, charlie, &[alice_bob]));
+            assert!(!helpers::verify_merkle_proof(root, helpers::merkle_leaf(accounts.alice, 101), &[bob, charlie]));
+        }
+
+        #[ink::test]
+        fn tier_allocation_is_skipped_outside_guaranteed_phase() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.bob);
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert_eq!(ido.grant_role(SUB_ADMIN, accounts.bob), Ok(()));
+            let guaranteed = types::Phase { kind: types::PhaseKind::Guaranteed, start: 0, end: 100, price: Some(types::Price::new(1, 1)), cap: 1000 };
+            let public = types::Phase { kind: types::PhaseKind::Public, start: 100, end: 200, price: None, cap: 1000 };
+            assert_eq!(ido.admin_set_phases(vec![guaranteed, public]), Ok(()));
+            assert_eq!(ido._spend_tier_allocation(accounts.charlie, Some(0), None, 10), Ok(()));
+
+            assert_eq!(ido.admin_set_tier_allocations(accounts.django, vec![0, 100, 200]), Ok(()));
+            assert_eq!(ido._spend_tier_allocation(accounts.charlie, Some(1), None, 10), Ok(()));
+            assert_eq!(ido.guaranteed_spent_of(accounts.charlie), 0);
+        }
+
+        #[ink::test]
+        fn overflow_allocation_is_pro_rata() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = sub_admin_pool();
+            assert_eq!(ido.admin_set_sale_mode(types::SaleMode::Overflow), Err(IDOError::InvalidCaps));
+            assert_eq!(ido.admin_set_caps(0, 1000), Ok(()));
+            assert_eq!(ido.admin_set_sale_mode(types::SaleMode::Overflow), Ok(()));
+            // the hard cap must stay within the issuable ido token at the pool price
+            assert_eq!(ido.admin_set_caps(0, 200_000), Err(IDOError::InvalidCaps));
+            assert_eq!(ido.admin_set_caps(0, 0), Err(IDOError::InvalidCaps));
+            assert_eq!(ido.admin_set_price(types::Price::new(1, 1000)), Err(IDOError::InvalidCaps));
+
+            ink::env::test::set_value_transferred::<Environment>(3000);
+            assert_eq!(ido.commit_native(), Err(IDOError::PoolNotFunded));
+            ido.ido.funded = true;
+            assert_eq!(ido.commit_native(), Ok(()));
+            ink::env::test::set_caller::<Environment>(accounts.charlie);
+            ink::env::test::set_value_transferred::<Environment>(1000);
+            assert_eq!(ido.commit_native(), Ok(()));
+
+            // 4000 committed against a hard cap of 1000, everyone keeps a quarter
+            assert_eq!(ido.committed_of(accounts.bob), 3000);
+            assert_eq!(ido.refundable_of(accounts.bob), 2250);
+            assert_eq!(ido.allocated_of(accounts.bob), Ok(750));
+            assert_eq!(ido.refundable_of(accounts.charlie), 750);
+            assert_eq!(ido.allocated_of(accounts.charlie), Ok(250));
+            assert_eq!(ido.settle(), Err(IDOError::SaleNotFinalized));
+        }
+
+        #[ink::test]
+        fn pause_and_cancel_sale_work() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.bob);
+            assert_eq!(ido._init_ido(accounts.django, accounts.alice, types::Price::new(1, 1), 12, 100_000), Ok(()));
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert!(ido.pause().is_err());
+            assert_eq!(ido.grant_role(PAUSER, accounts.bob), Ok(()));
+            assert_eq!(ido.pause(), Ok(()));
+            assert_eq!(ido.settle(), Err(IDOError::Custom(String::from("P::Paused"))));
+            assert_eq!(ido.unpause(), Ok(()));
+            assert_eq!(ido.settle(), Err(IDOError::WrongSaleMode));
+
+            assert_eq!(ido.refund(), Err(IDOError::NotRefunding));
+            assert_eq!(ido.cancel_sale(), Ok(()));
+            assert_eq!(ido.get_sale_status(), types::SaleStatus::Cancelled);
+            assert_eq!(ido.cancel_sale(), Err(IDOError::SaleClosed));
+            assert_eq!(ido.refund(), Err(IDOError::NothingToRefund));
+        }
+
+        #[ink::test]
+        fn refund_keeps_the_claimed_share() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = sub_admin_pool();
+            ink::env::test::set_account_balance::<Environment>(ink::env::account_id::<Environment>(), 1_000_000);
+            assert_eq!(ido._add_contribution(accounts.charlie, None, 1000), Ok(()));
+            assert!(ido._issue_ido(accounts.charlie, 1000).is_ok());
+            // charlie claims three quarters before the sale is cancelled
+            ido.ido.user_ido_balances.insert(&accounts.charlie, &250);
+            ido.ido.user_claimed.insert(&accounts.charlie, &750);
+
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert_eq!(ido.cancel_sale(), Ok(()));
+            ink::env::test::set_caller::<Environment>(accounts.charlie);
+            assert_eq!(ido.refund(), Ok(()));
+            assert_eq!(ido.contribution_of(accounts.charlie, None), 750);
+            assert_eq!(ido.user_info(accounts.charlie).contributed, 750);
+            assert_eq!(ido.get_total_raised(), 750);
+            assert_eq!(ido.get_ido_token_balance(accounts.charlie), 0);
+            assert_eq!(ido.refund(), Err(IDOError::NothingToRefund));
+        }
+
+        #[ink::test]
+        fn refund_share_handles_18_decimal_amounts() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.bob);
+            let ido_amount: Balance = 1_000_000_000_000_000_000_000_000;
+            assert_eq!(ido._init_ido(accounts.django, accounts.alice, types::Price::new(1, 1), 18, ido_amount), Ok(()));
+            ink::env::test::set_account_balance::<Environment>(ink::env::account_id::<Environment>(), 1_000_000_000_000_000_000);
+            // 1000 native units for 1_000_000 ido token with 18 decimals, half of it claimed
+            assert_eq!(ido._add_contribution(accounts.charlie, None, 1_000_000_000_000_000), Ok(()));
+            assert!(ido._issue_ido(accounts.charlie, ido_amount).is_ok());
+            ido.ido.user_ido_balances.insert(&accounts.charlie, &(ido_amount / 2));
+            ido.ido.user_claimed.insert(&accounts.charlie, &(ido_amount / 2));
+
+            ink::env::test::set_caller::<Environment>(accounts.bob);
+            assert_eq!(ido.cancel_sale(), Ok(()));
+            ink::env::test::set_caller::<Environment>(accounts.charlie);
+            assert_eq!(ido.refund(), Ok(()));
+            assert_eq!(ido.contribution_of(accounts.charlie, None), 500_000_000_000_000);
+            assert_eq!(ido.get_total_raised(), 500_000_000_000_000);
+        }
+
+        #[ink::test]
+        fn owed_ido_amount_excludes_claimed() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = sub_admin_pool();
+            assert!(ido._issue_ido(accounts.charlie, 700).is_ok());
+            ido.ido.total_claimed = 200;
+            assert_eq!(ido.owed_ido_amount(), Ok(500));
+
+            assert_eq!(ido.sweep_unsold(accounts.bob), Err(IDOError::SaleNotFinalized));
+        }
+
+        #[ink::test]
+        fn allocation_grant_can_be_revoked() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.alice);
+            assert_eq!(ido._init_ido(accounts.django, accounts.alice, types::Price::new(1, 1), 12, 100_000), Ok(()));
+            let grant = types::AllocationGrant { buyer: accounts.bob, max_allocation: 1000, phase: None, expiry: 100, epoch: 0 };
+            assert_ne!(
+                ido.gen_hash_for_allocation_grant(grant),
+                ido.gen_hash_for_allocation_grant(types::AllocationGrant { max_allocation: 2000, ..grant })
+            );
+            assert_eq!(ido.buy_ido_with_native_granted(grant, Vec::new(), 0), Err(IDOError::InvalidSignature));
+
+            assert_eq!(ido.grant_role(SIGNER_ADMIN, accounts.alice), Ok(()));
+            assert_eq!(ido.bump_allocation_epoch(), Ok(()));
+            assert_eq!(ido.buy_ido_with_native_granted(grant, Vec::new(), 0), Err(IDOError::GrantRevoked));
+            ink::env::test::set_block_timestamp::<Environment>(101);
+            assert_eq!(ido.buy_ido_with_native_granted(types::AllocationGrant { epoch: 1, ..grant }, Vec::new(), 0), Err(IDOError::Expired));
+        }
+
+        #[ink::test]
+        fn nonces_are_tracked_per_action() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.alice);
+            assert_eq!(ido._init_ido(accounts.django, accounts.alice, types::Price::new(1, 1), 12, 100_000), Ok(()));
+            assert_eq!(ido.invalidate_nonces_up_to(types::SignedAction::Claim, 4), Ok(()));
+            assert_eq!(ido.get_nonce(accounts.alice, types::SignedAction::Claim), 5);
+            assert_eq!(ido.get_nonce(accounts.alice, types::SignedAction::BuyWithNative), 0);
+            assert_eq!(ido.get_nonce(accounts.bob, types::SignedAction::Claim), 0);
+            assert_eq!(
+                ido.invalidate_nonces_up_to(types::SignedAction::Claim, 3),
+                Err(IDOError::InvalidNonce(String::from("3")))
+            );
+        }
+
+        #[ink::test]
+        fn sale_and_user_info_work() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = overflow_pool();
+            ink::env::test::set_value_transferred::<Environment>(300);
+            assert_eq!(ido.commit_native(), Ok(()));
+            assert_eq!(ido.commit_native(), Ok(()));
+
+            let info = ido.sale_info();
+            assert_eq!(info.participants, 1);
+            assert_eq!(info.total_raised, 600);
+            assert_eq!(info.max_issue_ido_amount, 100_000);
+            assert_eq!(info.status, types::SaleStatus::Active);
+            assert_eq!(ido.user_info(accounts.bob).contributed, 600);
+            assert_eq!(ido.user_info(accounts.charlie).contributed, 0);
+        }
+
+        #[ink::test]
+        fn participants_are_paginated() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = overflow_pool();
+            ink::env::test::set_value_transferred::<Environment>(300);
+            assert_eq!(ido.commit_native(), Ok(()));
+            ink::env::test::set_caller::<Environment>(accounts.charlie);
+            assert_eq!(ido.commit_native(), Ok(()));
             ink::env::test::set_caller::<Environment>(accounts.bob);
-            &ido.admin_set_price(20);
-            assert_eq!(ido.ido.price, 20);
+            assert_eq!(ido.commit_native(), Ok(()));
+
+            assert_eq!(ido.participants_count(), 2);
+            assert_eq!(ido.participants(0, 10), vec![(accounts.bob, 600, 0, 0), (accounts.charlie, 300, 0, 0)]);
+            assert_eq!(ido.participants(1, 1), vec![(accounts.charlie, 300, 0, 0)]);
+            assert_eq!(ido.participants(2, 10), vec![]);
+        }
+
+        #[ink::test]
+        fn dutch_auction_price_decays() {
+            let auction = types::DutchAuction {
+                start_price: 1000,
+                floor_price: 400,
+                price_denominator: 1,
+                start: 100,
+                end: 700,
+                step_duration: 0,
+                uniform_clearing: false,
+            };
+            assert!(auction.is_valid());
+            assert_eq!(auction.price_at(0), types::Price::new(1000, 1));
+            assert_eq!(auction.price_at(250), types::Price::new(850, 1));
+            assert_eq!(auction.price_at(700), types::Price::new(400, 1));
+
+            let stepped = types::DutchAuction { step_duration: 200, ..auction };
+            assert_eq!(stepped.price_at(250), types::Price::new(1000, 1));
+            assert_eq!(stepped.price_at(300), types::Price::new(800, 1));
+            assert_eq!(stepped.price_at(699), types::Price::new(600, 1));
+            assert!(!types::DutchAuction { floor_price: 1200, ..auction }.is_valid());
+        }
+
+        #[ink::test]
+        fn quotes_match_buy_math() {
+            let accounts = default_accounts::<ink::env::DefaultEnvironment>();
+            let mut ido = IdoContract::new(accounts.bob);
+            assert_eq!(ido._init_ido(accounts.django, accounts.alice, types::Price::new(2, 5), 12, 100_000), Ok(()));
+
+            // 2.5 ido token per native token
+            assert_eq!(ido.quote_ido_amount(1000), Ok(2500));
+            assert_eq!(ido.quote_payment_for(2500), Ok(1000));
+            assert_eq!(ido.quote_payment_for(2501), Ok(1001));
+        }
+
+        #[ink::test]
+        fn price_respects_token_decimals() {
+            // 0.4 native per ido token, 12 native decimals and 18 ido token decimals
+            let price = types::Price::new(2, 5);
+            let one_native = 1_000_000_000_000;
+            assert_eq!(price.ido_for_payment(one_native, 12, 18), Some(2_500_000_000_000_000_000));
+            assert_eq!(price.payment_for_ido(2_500_000_000_000_000_000, 12, 18, true), Some(one_native));
+            assert_eq!(price.ido_for_payment(u128::MAX, 0, 38), None);
+
+            assert_eq!(helpers::mul_div(u128::MAX, u128::MAX, u128::MAX, false), Some(u128::MAX));
+            assert_eq!(helpers::mul_div(u128::MAX, 3, 6, true), Some(u128::MAX / 2 + 1));
+            assert_eq!(helpers::mul_div(u128::MAX, 2, 1, false), None);
+            // the remainder times the numerator no longer fits in 128 bits
+            assert_eq!(
+                helpers::proportion(30_000_000_000_000_000_000, 20_000_000_000_000_000_000, 40_000_000_000_000_000_000),
+                15_000_000_000_000_000_000
+            );
+            assert_eq!(helpers::proportion(1000, 1, 0), 0);
         }
     }
 }
@@ -5,8 +5,11 @@ use openbrush::{
     },
 };
 use ink::prelude::string::String;
+use ink::prelude::vec::Vec;
 use openbrush::contracts::traits::access_control::AccessControlError;
+use openbrush::contracts::traits::pausable::PausableError;
 use openbrush::traits::{Timestamp};
+use crate::types::{AllocationMode, DutchAuction, Phase, Price, SignedAction, SaleMode, SaleStatus, SignatureMode, SignerId, VestingConfig};
 
 #[openbrush::wrapper]
 pub type IdoRef = dyn Ido;
@@ -14,32 +17,80 @@ pub type IdoRef = dyn Ido;
 #[openbrush::trait_definition]
 pub trait Ido {
     #[ink(message)]
-    fn init_ido(&mut self, _ido_token: AccountId, _signer: AccountId, _price: u128, _price_decimals: u32, _max_issue_ido_amount: u128) -> Result<(), IDOError>;
+    fn init_ido(&mut self, _ido_token: AccountId, _signer: AccountId, _price: Price, _max_issue_ido_amount: u128) -> Result<(), IDOError>;
 
     #[ink(message)]
     fn get_ido_token(&self) -> AccountId;
 
     #[ink(message, payable)]
-    fn buy_ido_with_native(&mut self, deadline: Timestamp, nonce: u128, signature: [u8; 65]) -> Result<(), IDOError>;
+    fn buy_ido_with_native(&mut self, beneficiary: AccountId, deadline: Timestamp, nonce: u128, min_ido_amount_out: Balance, signatures: Vec<[u8; 65]>) -> Result<(), IDOError>;
 
     #[ink(message)]
-    fn claim_ido_token(&mut self, deadline: Timestamp, nonce: u128, amount: Balance, signature: [u8; 65]) -> Result<(), IDOError>;
+    fn buy_ido_with_token(&mut self, payment_token: AccountId, amount: Balance, beneficiary: AccountId, deadline: Timestamp, nonce: u128, min_ido_amount_out: Balance, signatures: Vec<[u8; 65]>) -> Result<(), IDOError>;
 
     #[ink(message)]
-    fn admin_set_price(&mut self, new_price: u128) -> Result<(), IDOError>;
+    fn claim_ido_token(&mut self, recipient: AccountId, deadline: Timestamp, nonce: u128, amount: Balance, signatures: Vec<[u8; 65]>) -> Result<(), IDOError>;
 
     #[ink(message)]
-    fn get_price(&self) -> Balance;
+    fn admin_set_price(&mut self, new_price: Price) -> Result<(), IDOError>;
 
     #[ink(message)]
-    fn get_nonce(&self, account: AccountId) -> u128;
+    fn get_price(&self) -> Price;
+
+    #[ink(message)]
+    fn admin_set_token_price(&mut self, payment_token: AccountId, new_price: Price) -> Result<(), IDOError>;
+
+    #[ink(message)]
+    fn get_token_price(&self, payment_token: AccountId) -> Price;
+
+    #[ink(message)]
+    fn get_nonce(&self, account: AccountId, action: SignedAction) -> u128;
+
+    #[ink(message)]
+    fn invalidate_nonces_up_to(&mut self, action: SignedAction, nonce: u128) -> Result<(), IDOError>;
 }
 
 pub trait Internal {
-    fn _verify(&self, data: String, signer: AccountId, signature: [u8; 65]) -> bool;
-    fn _emit_buy_with_native_event(&self, _buyer: AccountId, _native_amount: Balance, _ido_token_amount: Balance, _nonce: u128);
-    fn _emit_claim_token_event(&self, _buyer: AccountId, _ido_token_amount: Balance, _nonce: u128);
-    fn _emit_init_ido_contract_event(&self, _ido_token: AccountId, _price: Balance, _price_decimals: u32, _signer: AccountId, _max_issue_ido_amount: u128);
+    fn _recover_signer(&self, message_hash: [u8; 32], signature: [u8; 65]) -> Option<SignerId>;
+    fn _is_signer(&self, signer: &SignerId) -> bool;
+    fn _verify_signatures(&self, message_hash: [u8; 32], signatures: &[[u8; 65]]) -> bool;
+    fn _emit_buy_with_native_event(&self, _payer: AccountId, _buyer: AccountId, _native_amount: Balance, _ido_token_amount: Balance, _nonce: u128);
+    fn _emit_buy_with_token_event(&self, _payer: AccountId, _buyer: AccountId, _payment_token: AccountId, _payment_amount: Balance, _ido_token_amount: Balance, _nonce: u128);
+    fn _emit_set_token_price_event(&self, _payment_token: AccountId, _price: Price);
+    fn _emit_claim_token_event(&self, _buyer: AccountId, _recipient: AccountId, _ido_token_amount: Balance, _nonce: u128);
+    fn _emit_set_vesting_event(&self, _vesting: VestingConfig);
+    fn _emit_set_phases_event(&self, _phases: Vec<Phase>);
+    fn _emit_phase_changed_event(&self, _index: u32, _phase: Phase);
+    fn _emit_set_caps_event(&self, _soft_cap: Balance, _hard_cap: Balance);
+    fn _emit_set_sale_end_event(&self, _sale_end: Timestamp);
+    fn _emit_sale_finalized_event(&self, _total_raised: Balance, _status: SaleStatus);
+    fn _emit_refunded_event(&self, _buyer: AccountId, _payment_token: Option<AccountId>, _amount: Balance, _ido_token_amount: Balance);
+    fn _emit_sale_cancelled_event(&self, _total_raised: Balance);
+    fn _emit_sale_tokens_recovered_event(&self, _to: AccountId, _amount: Balance);
+    fn _emit_unsold_swept_event(&self, _to: AccountId, _amount: Balance, _owed: Balance);
+    fn _emit_pool_funded_event(&self, _funder: AccountId, _amount: Balance, _balance: Balance);
+    fn _emit_allocation_epoch_bumped_event(&self, _epoch: u32);
+    fn _emit_nonces_invalidated_event(&self, _account: AccountId, _action: SignedAction, _next_nonce: u128);
+    fn _emit_grant_purchase_event(&self, _payer: AccountId, _buyer: AccountId, _payment_token: Option<AccountId>, _payment_amount: Balance, _ido_token_amount: Balance, _spent: Balance);
+    fn _emit_set_platform_fee_event(&self, _platform_fee: u32, _platform_treasury: AccountId);
+    fn _emit_withdraw_raised_event(&self, _to: AccountId, _payment_token: Option<AccountId>, _amount: Balance, _fee: Balance);
+    fn _emit_set_signature_mode_event(&self, _mode: SignatureMode, _eth_signer: [u8; 20]);
+    fn _emit_signer_added_event(&self, _signer: SignerId);
+    fn _emit_signer_removed_event(&self, _signer: SignerId);
+    fn _emit_signer_threshold_changed_event(&self, _threshold: u32);
+    fn _emit_signer_proposed_event(&self, _new_signer: AccountId, _activate_at: Timestamp);
+    fn _emit_signer_changed_event(&self, _old_signer: AccountId, _new_signer: AccountId, _grace_until: Timestamp);
+    fn _emit_eth_signer_changed_event(&self, _old_signer: [u8; 20], _new_signer: [u8; 20]);
+    fn _emit_set_allocation_mode_event(&self, _mode: AllocationMode);
+    fn _emit_set_merkle_root_event(&self, _merkle_root: [u8; 32]);
+    fn _emit_allowlist_purchase_event(&self, _buyer: AccountId, _payment_token: Option<AccountId>, _payment_amount: Balance, _ido_token_amount: Balance, _spent: Balance);
+    fn _emit_set_tier_allocations_event(&self, _staking_contract: AccountId, _tier_allocations: Vec<Balance>);
+    fn _emit_set_sale_mode_event(&self, _sale_mode: SaleMode);
+    fn _emit_committed_event(&self, _buyer: AccountId, _payment_token: Option<AccountId>, _amount: Balance, _committed: Balance);
+    fn _emit_settled_event(&self, _buyer: AccountId, _committed: Balance, _ido_token_amount: Balance);
+    fn _emit_set_dutch_auction_event(&self, _auction: DutchAuction);
+    fn _emit_auction_rebate_event(&self, _buyer: AccountId, _clearing_price: Price, _rebate: Balance);
+    fn _emit_init_ido_contract_event(&self, _ido_token: AccountId, _price: Price, _token_decimals: u8, _signer: AccountId, _max_issue_ido_amount: u128);
 }
 
 
@@ -54,10 +105,60 @@ pub enum IDOError {
     CommonError,
     Expired,
     InsufficientBalance,
+    InsufficientAllowance,
     Initialized,
+    UnsupportedPaymentToken,
+    ZeroAmount,
+    InvalidVestingConfig,
+    ExceedsClaimable,
+    InvalidPhaseConfig,
+    SaleNotOpen,
+    PhaseCapExceeded,
+    InvalidCaps,
+    HardCapExceeded,
+    SaleClosed,
+    SaleNotEnded,
+    SaleNotFinalized,
+    NotRefunding,
+    NothingToRefund,
+    NativeTransferError,
+    InvalidPlatformFee,
+    NothingToWithdraw,
+    InvariantViolated(String),
+    InvalidThreshold,
+    SignerExists,
+    SignerNotFound,
+    NoPendingSigner,
+    SignerRotationDelay,
+    WrongAllocationMode,
+    InvalidMerkleProof,
+    AllocationExceeded,
+    TierLookupFailed,
+    TierAllocationExceeded,
+    WrongSaleMode,
+    AlreadySettled,
+    NothingToSettle,
+    InvalidAuctionConfig,
+    NothingToRebate,
+    SlippageExceeded,
+    InvalidPrice,
+    ArithmeticOverflow,
+    TokenMetadataLookupFailed,
+    SaleNotCancelled,
+    PoolNotFunded,
+    AlreadyFunded,
+    NotInitialized,
+    LegacyMessageUnsupported,
+    GrantRevoked,
+    GrantPhaseMismatch,
+    ZeroTokenAddress,
+    ZeroSignerAddress,
+    InvalidTokenDecimals,
+    ZeroMaxIssueAmount,
+    VestingLocked,
+    PhasesLocked,
 }
 
-
 impl From<AccessControlError> for IDOError {
     fn from(error: AccessControlError) -> Self {
         match error {
@@ -68,3 +169,12 @@ impl From<AccessControlError> for IDOError {
     }
 }
 
+impl From<PausableError> for IDOError {
+    fn from(error: PausableError) -> Self {
+        match error {
+            PausableError::Paused => IDOError::Custom(String::from("P::Paused")),
+            PausableError::NotPaused => IDOError::Custom(String::from("P::NotPaused")),
+        }
+    }
+}
+
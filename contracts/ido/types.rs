@@ -1,12 +1,22 @@
 use ink::storage::Mapping;
+use ink::prelude::vec::Vec;
 use openbrush::{
     traits::{
         AccountId,
         ZERO_ADDRESS,
     },
 };
-use openbrush::traits::{Balance};
+use openbrush::traits::{Balance, Timestamp};
+use crate::helpers;
 
+pub const BASIS_POINTS: u32 = 10_000;
+/// decimals of the native currency
+pub const NATIVE_DECIMALS: u8 = 12;
+/// largest ido token decimals a pool accepts
+pub const MAX_TOKEN_DECIMALS: u8 = 24;
+pub const MONTH: Timestamp = 30 * 24 * 60 * 60 * 1000;
+/// version of the signed payload schema, bumped when a payload layout changes
+pub const SCHEMA_VERSION: u8 = 2;
 
 pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
 
@@ -14,26 +24,485 @@ pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
 #[openbrush::upgradeable_storage(STORAGE_KEY)]
 pub struct Data {
     pub ido_token: AccountId,
-    pub price: u128,
-    pub price_decimals: u32,
+    pub price: Price,
+    /// decimals of the ido token, read from its metadata at init
+    pub token_decimals: u8,
     pub signer: AccountId,
-    pub account_nonce: Mapping<AccountId, u128>,
+    /// next valid nonce of each account, one sequence per signed action
+    pub account_nonce: Mapping<(AccountId, SignedAction), u128>,
     pub user_ido_balances: Mapping<AccountId, Balance>,
     pub max_issue_ido_amount: u128,
     pub issued_ido_amount: u128,
+    pub total_claimed: Balance,
+    /// price of ido token in each PSP22 payment token, a token without a valid price is not accepted
+    pub token_prices: Mapping<AccountId, Price>,
+    pub payment_token_decimals: Mapping<AccountId, u8>,
+    pub payment_tokens: Vec<AccountId>,
+    pub vesting: VestingConfig,
+    pub user_claimed: Mapping<AccountId, Balance>,
+    pub phases: Vec<Phase>,
+    pub phase_issued: Mapping<u32, Balance>,
+    /// index of the phase seen by the last purchase, used to emit phase transitions
+    pub last_phase: Option<u32>,
+    /// end of a sale without phases, 0 keeps it open until an admin sets one
+    pub sale_end: Timestamp,
+    /// caps are expressed in native currency, PSP22 payments count with their value in native currency
+    pub soft_cap: Balance,
+    pub hard_cap: Balance,
+    pub total_raised: Balance,
+    /// contributions of each buyer keyed by payment token, `None` is the native currency
+    pub user_contributions: Mapping<(AccountId, Option<AccountId>), Balance>,
+    pub status: SaleStatus,
+    /// platform fee taken from raised funds on withdrawal, in basis points
+    pub platform_fee: u32,
+    pub platform_treasury: AccountId,
+    /// raised, withdrawn and fee amounts keyed by payment token, `None` is the native currency
+    pub raised: Mapping<Option<AccountId>, Balance>,
+    pub withdrawn: Mapping<Option<AccountId>, Balance>,
+    pub fees: Mapping<Option<AccountId>, Balance>,
+    /// chain identifier bound into signed payloads
+    pub chain_id: u32,
+    /// verify the underscore-joined string messages instead of the SCALE payloads, kept for migration
+    pub legacy_messages: bool,
+    pub signature_mode: SignatureMode,
+    /// H160 address of the signer, used in `SignatureMode::Ethereum`
+    pub eth_signer: [u8; 20],
+    /// signers trusted in addition to `signer` / `eth_signer`, only those matching the signature mode count
+    pub signers: Vec<SignerId>,
+    /// number of distinct signers that must sign an action
+    pub signer_threshold: u32,
+    /// proposed signer and the time it can be activated
+    pub pending_signer: Option<(AccountId, Timestamp)>,
+    /// replaced signer and the end of its grace period
+    pub previous_signer: Option<(AccountId, Timestamp)>,
+    pub signer_rotation_delay: Timestamp,
+    pub signer_grace_period: Timestamp,
+    pub allocation_mode: AllocationMode,
+    pub merkle_root: [u8; 32],
+    /// value in native currency spent by each account against its allowlist allocation
+    pub allowlist_spent: Mapping<AccountId, Balance>,
+    /// staking contract providing `tier_of`, zero address disables tier allocations
+    pub staking_contract: AccountId,
+    /// allocation in native currency of each staking tier during guaranteed phases
+    pub tier_allocations: Vec<Balance>,
+    pub guaranteed_spent: Mapping<AccountId, Balance>,
+    pub sale_mode: SaleMode,
+    /// value in native currency committed by each account to an overflow sale
+    pub committed: Mapping<AccountId, Balance>,
+    pub settled: Mapping<AccountId, bool>,
+    pub auction: DutchAuction,
+    /// lowest price paid in the auction so far
+    pub clearing_price: Price,
+    /// native currency paid and ido token bought in the auction by each account
+    pub auction_paid: Mapping<AccountId, Balance>,
+    pub auction_bought: Mapping<AccountId, Balance>,
+    pub auction_paid_total: Balance,
+    pub auction_bought_total: Balance,
+    pub rebate_claimed: Mapping<AccountId, bool>,
+    /// the pool holds `max_issue_ido_amount` ido token, purchases are rejected until then
+    pub funded: bool,
+    /// grants signed for an older epoch are revoked
+    pub allocation_epoch: u32,
+    /// value in native currency spent against each allocation grant, keyed by the grant hash
+    pub grant_spent: Mapping<[u8; 32], Balance>,
+    /// value in native currency contributed by each account and not refunded
+    pub contributed_value: Mapping<AccountId, Balance>,
+    pub participated: Mapping<AccountId, bool>,
+    /// accounts in order of their first contribution, indexed from 0
+    pub participant_at: Mapping<u32, AccountId>,
+    pub participant_count: u32,
 }
 
 impl Default for Data {
     fn default() -> Self {
         Self {
             ido_token: ZERO_ADDRESS.into(),
-            price: 0,
+            price: Price::default(),
             signer: ZERO_ADDRESS.into(),
-            price_decimals: 5,
+            token_decimals: 0,
             account_nonce: Mapping::default(),
             user_ido_balances: Mapping::new(),
             max_issue_ido_amount: 0,
             issued_ido_amount: 0,
+            total_claimed: 0,
+            token_prices: Mapping::default(),
+            payment_token_decimals: Mapping::default(),
+            payment_tokens: Vec::new(),
+            vesting: VestingConfig {
+                tge_percent: BASIS_POINTS,
+                ..Default::default()
+            },
+            user_claimed: Mapping::default(),
+            phases: Vec::new(),
+            phase_issued: Mapping::default(),
+            last_phase: None,
+            sale_end: 0,
+            soft_cap: 0,
+            hard_cap: 0,
+            total_raised: 0,
+            user_contributions: Mapping::default(),
+            status: SaleStatus::Active,
+            platform_fee: 0,
+            platform_treasury: ZERO_ADDRESS.into(),
+            raised: Mapping::default(),
+            withdrawn: Mapping::default(),
+            fees: Mapping::default(),
+            chain_id: 0,
+            legacy_messages: false,
+            signature_mode: SignatureMode::Substrate,
+            eth_signer: [0; 20],
+            signers: Vec::new(),
+            signer_threshold: 1,
+            pending_signer: None,
+            previous_signer: None,
+            signer_rotation_delay: 0,
+            signer_grace_period: 0,
+            allocation_mode: AllocationMode::Signature,
+            merkle_root: [0; 32],
+            allowlist_spent: Mapping::default(),
+            staking_contract: ZERO_ADDRESS.into(),
+            tier_allocations: Vec::new(),
+            guaranteed_spent: Mapping::default(),
+            sale_mode: SaleMode::Fixed,
+            committed: Mapping::default(),
+            settled: Mapping::default(),
+            auction: DutchAuction::default(),
+            clearing_price: Price::default(),
+            auction_paid: Mapping::default(),
+            auction_bought: Mapping::default(),
+            auction_paid_total: 0,
+            auction_bought_total: 0,
+            rebate_claimed: Mapping::default(),
+            funded: false,
+            allocation_epoch: 0,
+            grant_spent: Mapping::default(),
+            contributed_value: Mapping::default(),
+            participated: Mapping::default(),
+            participant_at: Mapping::default(),
+            participant_count: 0,
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+pub enum SaleMode {
+    /// buyers receive ido token at the price when they buy, until the hard cap
+    Fixed,
+    /// buyers commit any amount, after the close each gets `commit * hard_cap / total_committed`
+    /// worth of ido token and takes the rest back
+    Overflow,
+    /// buyers pay the decaying auction price in native currency
+    DutchAuction,
+}
+
+/// cost of one whole ido token in whole units of the payment currency, `numerator / denominator`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+pub struct Price {
+    pub numerator: u128,
+    pub denominator: u128,
+}
+
+impl Price {
+    pub fn new(numerator: u128, denominator: u128) -> Self {
+        Price { numerator, denominator }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.numerator > 0 && self.denominator > 0
+    }
+
+    /// ido token bought with `payment` at this price, rounded down, `None` on overflow
+    pub fn ido_for_payment(&self, payment: Balance, payment_decimals: u8, token_decimals: u8) -> Option<Balance> {
+        // ido = payment * 10^token_decimals * denominator / (10^payment_decimals * numerator)
+        let (up, down) = helpers::decimals_scale(token_decimals, payment_decimals)?;
+        helpers::mul_div(payment, self.denominator.checked_mul(up)?, self.numerator.checked_mul(down)?, false)
+    }
+
+    /// payment needed for `ido_amount` at this price, `None` on overflow
+    pub fn payment_for_ido(&self, ido_amount: Balance, payment_decimals: u8, token_decimals: u8, round_up: bool) -> Option<Balance> {
+        // payment = ido_amount * 10^payment_decimals * numerator / (10^token_decimals * denominator)
+        let (up, down) = helpers::decimals_scale(payment_decimals, token_decimals)?;
+        helpers::mul_div(ido_amount, self.numerator.checked_mul(up)?, self.denominator.checked_mul(down)?, round_up)
+    }
+}
+
+/// price of one ido token in native currency falling from `start_price / price_denominator` at `start`
+/// to `floor_price / price_denominator` at `end`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+pub struct DutchAuction {
+    pub start_price: u128,
+    pub floor_price: u128,
+    pub price_denominator: u128,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    /// the price drops every `step_duration`, 0 decays linearly
+    pub step_duration: Timestamp,
+    /// everyone settles at the clearing price and takes back the difference
+    pub uniform_clearing: bool,
+}
+
+impl DutchAuction {
+    pub fn is_valid(&self) -> bool {
+        self.start < self.end
+            && self.floor_price > 0
+            && self.price_denominator > 0
+            && self.floor_price <= self.start_price
+            && self.step_duration <= self.end - self.start
+    }
+
+    pub fn is_open(&self, now: Timestamp) -> bool {
+        self.start <= now && now < self.end
+    }
+
+    pub fn price_at(&self, now: Timestamp) -> Price {
+        Price::new(self.numerator_at(now), self.price_denominator)
+    }
+
+    fn numerator_at(&self, now: Timestamp) -> u128 {
+        if now <= self.start {
+            return self.start_price;
+        }
+        if now >= self.end {
+            return self.floor_price;
+        }
+        let mut elapsed = now - self.start;
+        if self.step_duration > 0 {
+            elapsed -= elapsed % self.step_duration;
+        }
+        let decay = helpers::proportion(self.start_price - self.floor_price, elapsed as u128, (self.end - self.start) as u128);
+        self.start_price - decay
+    }
+}
+
+/// how purchases are authorised
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+pub enum AllocationMode {
+    /// every purchase carries a fresh backend signature
+    Signature,
+    /// buyers prove `(account, max_allocation)` against the committed Merkle root
+    Merkle,
+}
+
+/// how signatures are checked against the signer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+pub enum SignatureMode {
+    /// Blake2x256 of the recovered public key is the signer `AccountId`
+    Substrate,
+    /// the recovered public key is turned into an Ethereum address and the hash is signed with the personal-sign prefix
+    Ethereum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+pub enum SignerId {
+    Substrate(AccountId),
+    Ethereum([u8; 20]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum SignedAction {
+    BuyWithNative,
+    BuyWithToken,
+    Claim,
+    AllocationGrant,
+}
+
+/// domain separator hashed together with every signed payload
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct Domain {
+    pub contract: AccountId,
+    pub action: SignedAction,
+    pub chain_id: u32,
+    pub version: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct BuyPayload {
+    pub ido_token: AccountId,
+    pub payer: AccountId,
+    pub beneficiary: AccountId,
+    pub amount: Balance,
+    pub deadline: Timestamp,
+    pub nonce: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct BuyWithTokenPayload {
+    pub ido_token: AccountId,
+    pub payment_token: AccountId,
+    pub payer: AccountId,
+    pub beneficiary: AccountId,
+    pub amount: Balance,
+    pub deadline: Timestamp,
+    pub nonce: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct ClaimPayload {
+    pub ido_token: AccountId,
+    pub buyer: AccountId,
+    pub recipient: AccountId,
+    pub amount: Balance,
+    pub deadline: Timestamp,
+    pub nonce: u128,
+}
+
+/// sale configuration and progress, returned by `sale_info`
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct SaleInfo {
+    pub ido_token: AccountId,
+    pub signer: AccountId,
+    pub price: Price,
+    pub token_decimals: u8,
+    pub max_issue_ido_amount: Balance,
+    pub issued_ido_amount: Balance,
+    pub total_claimed: Balance,
+    pub total_raised: Balance,
+    pub soft_cap: Balance,
+    pub hard_cap: Balance,
+    pub sale_mode: SaleMode,
+    pub allocation_mode: AllocationMode,
+    pub status: SaleStatus,
+    pub current_phase: Option<(u32, Phase)>,
+    pub sale_end: Timestamp,
+    pub participants: u32,
+    pub funded: bool,
+    pub paused: bool,
+}
+
+/// position of an account in the sale, returned by `user_info`
+#[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct UserInfo {
+    /// value in native currency contributed and not refunded
+    pub contributed: Balance,
+    pub purchased: Balance,
+    pub claimed: Balance,
+    pub claimable: Balance,
+    pub vested: Balance,
+    pub buy_nonce: u128,
+    pub buy_with_token_nonce: u128,
+    pub claim_nonce: u128,
+}
+
+/// signed allowance for `buyer` to spend up to `max_allocation` native value over several purchases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct AllocationGrant {
+    pub buyer: AccountId,
+    pub max_allocation: Balance,
+    /// index of the only phase the grant can be spent in, `None` for any phase
+    pub phase: Option<u32>,
+    pub expiry: Timestamp,
+    pub epoch: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+pub enum SaleStatus {
+    Active,
+    /// the sale ended and reached the soft cap
+    Finalized,
+    /// the sale ended below the soft cap, buyers can take their contributions back
+    Refunding,
+    /// the sale was stopped for good, buyers take their contributions back and the project its ido token
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+pub enum PhaseKind {
+    Guaranteed,
+    Fcfs,
+    Public,
+}
+
+/// sale round, open in `[start, end)`, timestamps are in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+pub struct Phase {
+    pub kind: PhaseKind,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    /// price of ido token in native currency in this phase, `None` keeps the pool price
+    pub price: Option<Price>,
+    /// max amount of ido token issued in this phase
+    pub cap: Balance,
+}
+
+impl Phase {
+    pub fn is_open(&self, now: Timestamp) -> bool {
+        self.start <= now && now < self.end
+    }
+}
+
+/// phases must be non-empty windows sorted by time and must not overlap
+pub fn is_valid_phases(phases: &[Phase]) -> bool {
+    for i in 0..phases.len() {
+        if phases[i].start >= phases[i].end {
+            return false;
+        }
+        if i > 0 && phases[i - 1].end > phases[i].start {
+            return false;
+        }
+        if phases[i].price.map_or(false, |price| !price.is_valid()) {
+            return false;
+        }
+    }
+    true
+}
+
+/// unlock schedule of purchased ido tokens, timestamps are in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+pub struct VestingConfig {
+    /// part of the purchased amount unlocked at TGE, in basis points
+    pub tge_percent: u32,
+    pub tge_timestamp: Timestamp,
+    /// the rest is locked until the cliff and then released over `linear_duration`
+    pub cliff_timestamp: Timestamp,
+    pub linear_duration: Timestamp,
+    /// release the linear part in whole months instead of continuously
+    pub monthly_step: bool,
+}
+
+impl VestingConfig {
+    pub fn is_valid(&self) -> bool {
+        self.tge_percent <= BASIS_POINTS && self.cliff_timestamp >= self.tge_timestamp
+    }
+
+    /// amount of `total` unlocked at `now`
+    pub fn vested_amount(&self, total: Balance, now: Timestamp) -> Balance {
+        if now < self.tge_timestamp {
+            return 0;
+        }
+        let tge_amount = helpers::proportion(total, self.tge_percent as u128, BASIS_POINTS as u128);
+        if now < self.cliff_timestamp {
+            return tge_amount;
+        }
+        let elapsed = now - self.cliff_timestamp;
+        if self.linear_duration == 0 || elapsed >= self.linear_duration {
+            return total;
+        }
+
+        let locked = total - tge_amount;
+        let steps = self.linear_duration / MONTH;
+        let released = if self.monthly_step && steps > 0 {
+            helpers::proportion(locked, (elapsed / MONTH).min(steps) as u128, steps as u128)
+        } else {
+            helpers::proportion(locked, elapsed as u128, self.linear_duration as u128)
+        };
+        tge_amount + released
+    }
+}
@@ -16,7 +16,7 @@ pub mod factory {
 
     use crate::traits::{*};
     use crate::types::{*};
-    use ido::traits::{IdoRef};
+    use ido::types::{Price};
     use ido::ido::{IdoContractRef};
 
     pub const DEPLOYER: RoleType = ink::selector_id!("DEPLOYER");
@@ -53,9 +53,8 @@ pub mod factory {
 
         #[ink(message)]
         #[modifiers(only_role(DEPLOYER))]
-        fn create_pool(&mut self, ido_token: AccountId, signer: AccountId, price: u128, price_decimals: u32, max_issue_ido_amount: u128) -> Result<AccountId, FactoryError> {
-            let pool_contract = self._instantiate_pool()?;
-            IdoRef::init_ido(&pool_contract, ido_token, signer, price, price_decimals, max_issue_ido_amount).map_err(|_| FactoryError::PoolInitFailed).unwrap();
+        fn create_pool(&mut self, ido_token: AccountId, signer: AccountId, price: Price, max_issue_ido_amount: u128) -> Result<AccountId, FactoryError> {
+            let pool_contract = self._instantiate_pool(ido_token, signer, price, max_issue_ido_amount)?;
 
             let index = self.factory.pool_length;
             self.factory
@@ -87,16 +86,20 @@ pub mod factory {
             instance
         }
 
-        fn _instantiate_pool(&mut self) -> Result<AccountId, FactoryError> {
+        /// deploys a pool configured in its constructor, so it is never left uninitialised
+        fn _instantiate_pool(&mut self, ido_token: AccountId, signer: AccountId, price: Price, max_issue_ido_amount: u128) -> Result<AccountId, FactoryError> {
             let salt = (self.env().block_timestamp(), b"ido_factory").encode();
             let hash = xxh32(&salt, 0).to_le_bytes();
 
             let pool_hash = self.factory.pool_contract_code_hash;
-            let pool = IdoContractRef::new(self.env().caller())
+            let pool = IdoContractRef::new_with_config(self.env().caller(), ido_token, signer, price, max_issue_ido_amount)
                 .endowment(0)
                 .code_hash(pool_hash)
                 .salt_bytes(&hash[..4])
-                .try_instantiate().map_err(|_| FactoryError::PoolInstantiationFailed).unwrap().unwrap();
+                .try_instantiate()
+                .map_err(|_| FactoryError::PoolInstantiationFailed)?
+                .map_err(|_| FactoryError::PoolInstantiationFailed)?
+                .map_err(|_| FactoryError::PoolInitFailed)?;
             Ok(pool.to_account_id())
         }
 
@@ -132,7 +135,7 @@ pub mod factory {
             ink::env::debug_println!("data {:?}", DEPLOYER);
             let accounts = default_accounts::<ink::env::DefaultEnvironment>();
             let mut factory = FactoryContract::new(Hash::default());
-            let pool_address = factory.create_pool(accounts.alice, accounts.alice, 100, 10, 100000).unwrap();
+            let pool_address = factory.create_pool(accounts.alice, accounts.alice, Price::new(1, 10), 100000).unwrap();
         }
     }
 }
@@ -6,6 +6,7 @@ use openbrush::{
 use ink::prelude::string::String;
 use openbrush::traits::{Hash};
 use openbrush::contracts::traits::access_control::AccessControlError;
+use ido::types::Price;
 
 #[openbrush::wrapper]
 pub type FactoryRef = dyn Factory;
@@ -26,8 +27,7 @@ pub trait Factory {
         &mut self,
         ido_token: AccountId,
         signer: AccountId,
-        price: u128,
-        price_decimals: u32,
+        price: Price,
         max_issue_ido_amount: u128,
     ) -> Result<AccountId, FactoryError>;
 }
@@ -29,6 +29,56 @@ mod staking {
         InsufficientBalance,
         InvalidSignature,
         OnlyOwner,
+        InvalidThreshold,
+        SignerExists,
+        SignerNotFound,
+    }
+
+    /// version of the signed payload schema, bumped when a payload layout changes
+    pub const SCHEMA_VERSION: u8 = 1;
+
+    /// how signatures are checked against the signer
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub enum SignatureMode {
+        /// Blake2x256 of the recovered public key is the signer `AccountId`
+        Substrate,
+        /// the recovered public key is turned into an Ethereum address and the hash is signed with the personal-sign prefix
+        Ethereum,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub enum SignerId {
+        Substrate(AccountId),
+        Ethereum([u8; 20]),
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum SignedAction {
+        Stake,
+        Unstake,
+    }
+
+    /// domain separator hashed together with every signed payload
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Domain {
+        pub contract: AccountId,
+        pub action: SignedAction,
+        pub chain_id: u32,
+        pub version: u8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct StakePayload {
+        pub stake_token: AccountId,
+        pub staker: AccountId,
+        pub amount: u128,
+        pub deadline: Timestamp,
+        pub nonce: u128,
     }
 
     #[ink(storage)]
@@ -38,8 +88,17 @@ mod staking {
         staking_amounts: Mapping<AccountId, u128>,
         account_tiers: Mapping<AccountId, u128>,
         tier_configs: Vec<u128>,
-        account_nonce: Mapping<AccountId, u128>,
+        /// next valid nonce of each account, one sequence per signed action
+        account_nonce: Mapping<(AccountId, SignedAction), u128>,
         signer: AccountId,
+        chain_id: u32,
+        legacy_messages: bool,
+        signature_mode: SignatureMode,
+        eth_signer: [u8; 20],
+        /// signers trusted in addition to `signer` / `eth_signer`, only those matching the signature mode count
+        signers: Vec<SignerId>,
+        /// number of distinct signers that must sign an action
+        signer_threshold: u32,
     }
 
     #[ink(event)]
@@ -65,10 +124,43 @@ mod staking {
         pub tiers: Vec<u128>,
     }
 
+    #[ink(event)]
+    pub struct SignerAddedEvent {
+        pub signer: SignerId,
+    }
+
+    #[ink(event)]
+    pub struct SignerRemovedEvent {
+        pub signer: SignerId,
+    }
+
+    #[ink(event)]
+    pub struct SignerThresholdChangedEvent {
+        pub threshold: u32,
+    }
+
+    #[ink(event)]
+    pub struct SignatureModeChangedEvent {
+        pub mode: SignatureMode,
+        pub eth_signer: [u8; 20],
+    }
+
+    #[ink(event)]
+    pub struct NoncesInvalidatedEvent {
+        pub account: AccountId,
+        pub action: SignedAction,
+        pub next_nonce: u128,
+    }
+
     pub trait Internal {
         fn _emit_staking_event(&self, account: AccountId, nonce: u128, amount: u128, new_tier: u128, timestamp: Timestamp);
         fn _emit_unstaking_event(&self, account: AccountId, nonce: u128, amount: u128, new_tier: u128, timestamp: Timestamp);
         fn _emit_set_tiers_event(&self, tiers: Vec<u128>);
+        fn _emit_signer_added_event(&self, signer: SignerId);
+        fn _emit_signer_removed_event(&self, signer: SignerId);
+        fn _emit_signer_threshold_changed_event(&self, threshold: u32);
+        fn _emit_nonces_invalidated_event(&self, account: AccountId, action: SignedAction, next_nonce: u128);
+        fn _emit_signature_mode_changed_event(&self, mode: SignatureMode, eth_signer: [u8; 20]);
     }
 
     impl Internal for Staking {
@@ -97,6 +189,39 @@ mod staking {
                 tiers,
             })
         }
+
+        fn _emit_signer_added_event(&self, signer: SignerId) {
+            self.env().emit_event(SignerAddedEvent {
+                signer,
+            })
+        }
+
+        fn _emit_signer_removed_event(&self, signer: SignerId) {
+            self.env().emit_event(SignerRemovedEvent {
+                signer,
+            })
+        }
+
+        fn _emit_signer_threshold_changed_event(&self, threshold: u32) {
+            self.env().emit_event(SignerThresholdChangedEvent {
+                threshold,
+            })
+        }
+
+        fn _emit_nonces_invalidated_event(&self, account: AccountId, action: SignedAction, next_nonce: u128) {
+            self.env().emit_event(NoncesInvalidatedEvent {
+                account,
+                action,
+                next_nonce,
+            })
+        }
+
+        fn _emit_signature_mode_changed_event(&self, mode: SignatureMode, eth_signer: [u8; 20]) {
+            self.env().emit_event(SignatureModeChangedEvent {
+                mode,
+                eth_signer,
+            })
+        }
     }
 
     impl Staking {
@@ -114,6 +239,12 @@ mod staking {
                 account_nonce: Mapping::default(),
                 tier_configs,
                 signer,
+                chain_id: 0,
+                legacy_messages: false,
+                signature_mode: SignatureMode::Substrate,
+                eth_signer: [0; 20],
+                signers: Vec::new(),
+                signer_threshold: 1,
             }
         }
 
@@ -128,21 +259,117 @@ mod staking {
             Ok(())
         }
 
+        /// the function allows the owner to choose how signatures are verified, `eth_signer` is the H160 signer used in Ethereum mode
+        #[ink(message)]
+        pub fn set_signature_mode(&mut self, mode: SignatureMode, eth_signer: [u8; 20]) -> Result<(), StakingError> {
+            let caller = self.env().caller();
+            if caller != self.owner {
+                return Err(StakingError::OnlyOwner.into());
+            }
+            self.signature_mode = mode;
+            self.eth_signer = eth_signer;
+            // only the signers of the new mode can approve actions from now on
+            if (self.get_signers().len() as u32) < self.signer_threshold {
+                return Err(StakingError::InvalidThreshold);
+            }
+            self._emit_signature_mode_changed_event(mode, eth_signer);
+            Ok(())
+        }
+
+        /// function to get the signature mode and the H160 signer
+        #[ink(message)]
+        pub fn get_signature_mode(&self) -> (SignatureMode, [u8; 20]) {
+            (self.signature_mode, self.eth_signer)
+        }
+
+        /// the function allows the owner to add a signer to the signer set
+        #[ink(message)]
+        pub fn add_signer(&mut self, signer: SignerId) -> Result<(), StakingError> {
+            let caller = self.env().caller();
+            if caller != self.owner {
+                return Err(StakingError::OnlyOwner.into());
+            }
+            if self.signers.contains(&signer) {
+                return Err(StakingError::SignerExists);
+            }
+            self.signers.push(signer);
+            self._emit_signer_added_event(signer);
+            Ok(())
+        }
+
+        /// the function allows the owner to remove a signer, the remaining signers must still reach the threshold
+        #[ink(message)]
+        pub fn remove_signer(&mut self, signer: SignerId) -> Result<(), StakingError> {
+            let caller = self.env().caller();
+            if caller != self.owner {
+                return Err(StakingError::OnlyOwner.into());
+            }
+            let index = self.signers.iter().position(|s| *s == signer).ok_or(StakingError::SignerNotFound)?;
+            self.signers.remove(index);
+            if (self.get_signers().len() as u32) < self.signer_threshold {
+                return Err(StakingError::InvalidThreshold);
+            }
+            self._emit_signer_removed_event(signer);
+            Ok(())
+        }
+
+        /// the function allows the owner to set how many distinct signers must sign an action
+        #[ink(message)]
+        pub fn set_signer_threshold(&mut self, threshold: u32) -> Result<(), StakingError> {
+            let caller = self.env().caller();
+            if caller != self.owner {
+                return Err(StakingError::OnlyOwner.into());
+            }
+            if threshold == 0 || threshold > self.get_signers().len() as u32 {
+                return Err(StakingError::InvalidThreshold);
+            }
+            self.signer_threshold = threshold;
+            self._emit_signer_threshold_changed_event(threshold);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn get_signer_threshold(&self) -> u32 {
+            self.signer_threshold
+        }
+
+        /// function to get the signers accepted in the current signature mode
+        #[ink(message)]
+        pub fn get_signers(&self) -> Vec<SignerId> {
+            let mut signers: Vec<SignerId> = Vec::new();
+            let primary = match self.signature_mode {
+                SignatureMode::Substrate => SignerId::Substrate(self.signer),
+                SignatureMode::Ethereum => SignerId::Ethereum(self.eth_signer),
+            };
+            signers.push(primary);
+            for signer in self.signers.iter() {
+                let same_mode = match (signer, self.signature_mode) {
+                    (SignerId::Substrate(_), SignatureMode::Substrate) => true,
+                    (SignerId::Ethereum(_), SignatureMode::Ethereum) => true,
+                    _ => false,
+                };
+                if same_mode && !signers.contains(signer) {
+                    signers.push(*signer);
+                }
+            }
+            signers
+        }
+
         /// function staking, after user call the API to get the signature for staking (BE API will sign the message), use will call this function to stake
         #[ink(message)]
-        pub fn stake(&mut self, deadline: Timestamp, nonce: u128, amount: u128, signature: [u8; 65]) -> Result<(), StakingError> {
+        pub fn stake(&mut self, deadline: Timestamp, nonce: u128, amount: u128, signatures: Vec<[u8; 65]>) -> Result<(), StakingError> {
             let caller = self.env().caller();
             if deadline < self.env().block_timestamp() {
                 return Err(StakingError::InvalidDeadline);
             }
-            if nonce != self.account_nonce.get(&caller).unwrap_or(0) {
+            if nonce != self.get_nonce(caller, SignedAction::Stake) {
                 return Err(StakingError::InvalidNonce(nonce.to_string()));
             }
-            self.account_nonce.insert(&caller, &(nonce + 1));
+            self.account_nonce.insert(&(caller, SignedAction::Stake), &(nonce + 1));
 
-            let message = self.gen_msg_for_stake_token(deadline, nonce, amount);
+            let message_hash = self.gen_hash_for_stake_token(deadline, nonce, amount);
             // verify signature
-            let is_ok = self._verify(message, self.signer, signature);
+            let is_ok = self._verify_signatures(message_hash, &signatures);
 
             if !is_ok {
                 return Err(StakingError::InvalidSignature);
@@ -172,19 +399,19 @@ mod staking {
 
         /// function unstaking, after user call the API to get the signature for unstaking (BE API will sign the message), use will call this function to unstake
         #[ink(message)]
-        pub fn unstake(&mut self, deadline: Timestamp, nonce: u128, amount: u128, signature: [u8; 65]) -> Result<(), StakingError> {
+        pub fn unstake(&mut self, deadline: Timestamp, nonce: u128, amount: u128, signatures: Vec<[u8; 65]>) -> Result<(), StakingError> {
             let caller = self.env().caller();
             if deadline < self.env().block_timestamp() {
                 return Err(StakingError::InvalidDeadline);
             }
-            if nonce != self.account_nonce.get(&caller).unwrap_or(0) {
+            if nonce != self.get_nonce(caller, SignedAction::Unstake) {
                 return Err(StakingError::InvalidNonce(nonce.to_string()));
             }
 
-            self.account_nonce.insert(&caller, &(nonce + 1));
-            let message = self.gen_msg_for_unstake_token(deadline, nonce, amount);
+            self.account_nonce.insert(&(caller, SignedAction::Unstake), &(nonce + 1));
+            let message_hash = self.gen_hash_for_unstake_token(deadline, nonce, amount);
             // verify signature
-            let is_ok = self._verify(message, self.signer, signature);
+            let is_ok = self._verify_signatures(message_hash, &signatures);
 
             if !is_ok {
                 return Err(StakingError::InvalidSignature);
@@ -218,10 +445,22 @@ mod staking {
             self.stake_token
         }
 
+        /// function to get the next valid nonce of the account for the signed action
         #[ink(message)]
-        pub fn get_nonce(&self) -> u128 {
+        pub fn get_nonce(&self, account: AccountId, action: SignedAction) -> u128 {
+            self.account_nonce.get(&(account, action)).unwrap_or(0)
+        }
+
+        /// function to revoke every signature of the caller for the action with a nonce up to `nonce`
+        #[ink(message)]
+        pub fn invalidate_nonces_up_to(&mut self, action: SignedAction, nonce: u128) -> Result<(), StakingError> {
             let caller = self.env().caller();
-            self.account_nonce.get(&caller).unwrap_or(0)
+            if nonce < self.get_nonce(caller, action) || nonce == u128::MAX {
+                return Err(StakingError::InvalidNonce(nonce.to_string()));
+            }
+            self.account_nonce.insert(&(caller, action), &(nonce + 1));
+            self._emit_nonces_invalidated_event(caller, action, nonce + 1);
+            Ok(())
         }
 
         /// function to get the owner of the staking contract
@@ -333,28 +572,100 @@ mod staking {
             message
         }
 
-        fn _verify(&self, data: String, signer: AccountId, signature: [u8; 65]) -> bool {
-            ink::env::debug_println!("data {:?}", data);
-            ink::env::debug_println!("signer {:?}", signer);
-            ink::env::debug_println!("signature {:?}", signature);
-
-            let mut message_hash = <hash::Blake2x256 as hash::HashOutput>::Type::default();
-            ink::env::hash_bytes::<hash::Blake2x256>(&data.as_bytes(), &mut message_hash);
+        /// function to set the chain identifier bound into signed payloads and whether the legacy string messages are verified instead
+        #[ink(message)]
+        pub fn set_signature_domain(&mut self, chain_id: u32, legacy_messages: bool) -> Result<(), StakingError> {
+            let caller = self.env().caller();
+            if caller != self.owner {
+                return Err(StakingError::OnlyOwner.into());
+            }
+            self.chain_id = chain_id;
+            self.legacy_messages = legacy_messages;
+            Ok(())
+        }
 
-            ink::env::debug_println!("message_hash {:?}", message_hash);
+        /// function to get the chain identifier and the legacy messages flag
+        #[ink(message)]
+        pub fn get_signature_domain(&self) -> (u32, bool) {
+            (self.chain_id, self.legacy_messages)
+        }
 
-            let output = self.env().ecdsa_recover(&signature, &message_hash).expect("Failed to recover");
+        /// function to get the hash the signer signs for `stake`
+        #[ink(message)]
+        pub fn gen_hash_for_stake_token(&self, deadline: Timestamp, nonce: u128, stake_amount: u128) -> [u8; 32] {
+            if self.legacy_messages {
+                return self._hash_message(&self.gen_msg_for_stake_token(deadline, nonce, stake_amount));
+            }
+            self._hash_payload(SignedAction::Stake, deadline, nonce, stake_amount)
+        }
 
-            ink::env::debug_println!("pubkey {:?}", output);
+        /// function to get the hash the signer signs for `unstake`
+        #[ink(message)]
+        pub fn gen_hash_for_unstake_token(&self, deadline: Timestamp, nonce: u128, unstake_amount: u128) -> [u8; 32] {
+            if self.legacy_messages {
+                return self._hash_message(&self.gen_msg_for_unstake_token(deadline, nonce, unstake_amount));
+            }
+            self._hash_payload(SignedAction::Unstake, deadline, nonce, unstake_amount)
+        }
 
-            let mut signature_account_id = <hash::Blake2x256 as hash::HashOutput>::Type::default();
-            ink::env::hash_encoded::<hash::Blake2x256, _>(&output, &mut signature_account_id);
+        fn _hash_payload(&self, action: SignedAction, deadline: Timestamp, nonce: u128, amount: u128) -> [u8; 32] {
+            let domain = Domain {
+                contract: self.env().account_id(),
+                action,
+                chain_id: self.chain_id,
+                version: SCHEMA_VERSION,
+            };
+            let payload = StakePayload {
+                stake_token: self.stake_token,
+                staker: self.env().caller(),
+                amount,
+                deadline,
+                nonce,
+            };
+            let mut message_hash = <hash::Blake2x256 as hash::HashOutput>::Type::default();
+            ink::env::hash_encoded::<hash::Blake2x256, _>(&(domain, payload), &mut message_hash);
+            message_hash
+        }
 
-            ink::env::debug_println!("Sig account id {:?}", AccountId::from(signature_account_id));
+        fn _hash_message(&self, message: &str) -> [u8; 32] {
+            let mut message_hash = <hash::Blake2x256 as hash::HashOutput>::Type::default();
+            ink::env::hash_bytes::<hash::Blake2x256>(message.as_bytes(), &mut message_hash);
+            message_hash
+        }
 
-            signer == AccountId::from(signature_account_id)
+        fn _recover_signer(&self, message_hash: [u8; 32], signature: [u8; 65]) -> Option<SignerId> {
+            match self.signature_mode {
+                SignatureMode::Substrate => {
+                    let output = self.env().ecdsa_recover(&signature, &message_hash).ok()?;
+                    let mut signature_account_id = <hash::Blake2x256 as hash::HashOutput>::Type::default();
+                    ink::env::hash_encoded::<hash::Blake2x256, _>(&output, &mut signature_account_id);
+                    Some(SignerId::Substrate(AccountId::from(signature_account_id)))
+                }
+                SignatureMode::Ethereum => {
+                    // Keccak256 of the hash with the Ethereum personal-sign prefix, as produced by `eth_sign`
+                    let mut prefixed: Vec<u8> = Vec::from(&b"\x19Ethereum Signed Message:\n32"[..]);
+                    prefixed.extend_from_slice(&message_hash);
+                    let mut eth_hash = <hash::Keccak256 as hash::HashOutput>::Type::default();
+                    ink::env::hash_bytes::<hash::Keccak256>(&prefixed, &mut eth_hash);
+
+                    let output = self.env().ecdsa_recover(&signature, &eth_hash).ok()?;
+                    self.env().ecdsa_to_eth_address(&output).ok().map(SignerId::Ethereum)
+                }
+            }
         }
 
+        fn _verify_signatures(&self, message_hash: [u8; 32], signatures: &[[u8; 65]]) -> bool {
+            let signers = self.get_signers();
+            let mut approvals: Vec<SignerId> = Vec::new();
+            for signature in signatures {
+                if let Some(signer) = self._recover_signer(message_hash, *signature) {
+                    if signers.contains(&signer) && !approvals.contains(&signer) {
+                        approvals.push(signer);
+                    }
+                }
+            }
+            approvals.len() as u32 >= self.signer_threshold
+        }
     }
 
     #[cfg(test)]
@@ -370,5 +681,74 @@ mod staking {
             staking.set_tiers(vec![100, 200, 300, 400, 500, 600, 700, 800, 900, 1000]);
             // assert_eq!(staking.get_tiers(c).unwrap(), vec![100, 200, 300, 400, 500, 600, 700, 800, 900, 1000]);
         }
+
+        #[ink::test]
+        fn signer_threshold_is_validated() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mut staking = Staking::new(accounts.alice, accounts.alice, Vec::new());
+            assert_eq!(staking.set_signer_threshold(2), Err(StakingError::InvalidThreshold));
+            assert_eq!(staking.add_signer(SignerId::Substrate(accounts.bob)), Ok(()));
+            assert_eq!(staking.add_signer(SignerId::Substrate(accounts.bob)), Err(StakingError::SignerExists));
+            assert_eq!(
+                staking.get_signers(),
+                vec![SignerId::Substrate(accounts.alice), SignerId::Substrate(accounts.bob)]
+            );
+            assert_eq!(staking.set_signer_threshold(0), Err(StakingError::InvalidThreshold));
+            assert_eq!(staking.set_signer_threshold(2), Ok(()));
+            assert_eq!(staking.get_signer_threshold(), 2);
+
+            assert_eq!(staking.remove_signer(SignerId::Substrate(accounts.charlie)), Err(StakingError::SignerNotFound));
+            // removing bob would leave fewer signers than the threshold
+            assert_eq!(staking.remove_signer(SignerId::Substrate(accounts.bob)), Err(StakingError::InvalidThreshold));
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(staking.set_signer_threshold(1), Err(StakingError::OnlyOwner));
+            assert_eq!(staking.remove_signer(SignerId::Substrate(accounts.bob)), Err(StakingError::OnlyOwner));
+        }
+
+        #[ink::test]
+        fn ethereum_signatures_are_recovered() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mut staking = Staking::new(accounts.alice, accounts.alice, Vec::new());
+            // personal-sign of [1; 32] with the private key 1
+            let eth_signer: [u8; 20] = hex::decode("7e5f4552091a69125d5dfcb7b8c2659029395bdf").unwrap().try_into().unwrap();
+            let signature: [u8; 65] = hex::decode(
+                "efa29f89d9851433e6f357243636876fa79968c86e8bc750cd45371a347d469d063dd56b7cf04160b95bf23aa4ddbec9a8891a63f224a4ab09645ef5fe2ff64c1b"
+            ).unwrap().try_into().unwrap();
+
+            assert_eq!(staking.set_signature_mode(SignatureMode::Ethereum, eth_signer), Ok(()));
+            assert_eq!(staking._recover_signer([1; 32], signature), Some(SignerId::Ethereum(eth_signer)));
+            assert!(staking._verify_signatures([1; 32], &[signature]));
+            assert!(!staking._verify_signatures([2; 32], &[signature]));
+
+            // a second Substrate signer does not count once Ethereum signatures are required
+            assert_eq!(staking.set_signature_mode(SignatureMode::Substrate, eth_signer), Ok(()));
+            assert_eq!(staking.add_signer(SignerId::Substrate(accounts.bob)), Ok(()));
+            assert_eq!(staking.set_signer_threshold(2), Ok(()));
+            assert_eq!(staking.set_signature_mode(SignatureMode::Ethereum, eth_signer), Err(StakingError::InvalidThreshold));
+        }
+
+        #[ink::test]
+        fn nonces_are_tracked_per_action() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mut staking = Staking::new(accounts.alice, accounts.alice, Vec::new());
+            assert_eq!(staking.invalidate_nonces_up_to(SignedAction::Stake, 4), Ok(()));
+            assert_eq!(staking.get_nonce(accounts.alice, SignedAction::Stake), 5);
+            assert_eq!(staking.get_nonce(accounts.alice, SignedAction::Unstake), 0);
+            assert_eq!(staking.get_nonce(accounts.bob, SignedAction::Stake), 0);
+
+            // the unstake nonce is untouched, so unstaking only fails on the missing signatures
+            assert_eq!(staking.stake(0, 0, 100, Vec::new()), Err(StakingError::InvalidNonce(String::from("0"))));
+            assert_eq!(staking.unstake(0, 0, 100, Vec::new()), Err(StakingError::InvalidSignature));
+
+            assert_eq!(
+                staking.invalidate_nonces_up_to(SignedAction::Stake, 3),
+                Err(StakingError::InvalidNonce(String::from("3")))
+            );
+            assert_eq!(
+                staking.invalidate_nonces_up_to(SignedAction::Unstake, u128::MAX),
+                Err(StakingError::InvalidNonce(u128::MAX.to_string()))
+            );
+        }
     }
 }